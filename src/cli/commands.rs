@@ -7,6 +7,20 @@ pub fn get_commands() -> Command {
         .about("git written in Rust")
         .subcommand_required(true)
         .arg_required_else_help(true)
+        .arg(
+            Arg::new("directory")
+                .short('C')
+                .value_name("PATH")
+                .help("run as if rit was started in <PATH>")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("rit-dir")
+                .long("rit-dir")
+                .value_name("PATH")
+                .help("path to the rit dir, overrides RIT_DIR")
+                .value_parser(value_parser!(PathBuf)),
+        )
         // add command
         .subcommand(
             Command::new("add")
@@ -30,7 +44,23 @@ pub fn get_commands() -> Command {
         // init command
         .subcommand(Command::new("init").about("initialize a repo"))
         // status command
-        .subcommand(Command::new("status").about("checks the status of rit dir"))
+        .subcommand(
+            Command::new("status")
+                .about("checks the status of rit dir")
+                .arg(
+                    Arg::new("porcelain")
+                        .long("porcelain")
+                        .help("Give the output in a stable \"XY <PATH>\" format for scripts")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Give the output as JSON")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("porcelain"),
+                ),
+        )
         // commit command
         .subcommand(
             Command::new("commit")
@@ -38,8 +68,243 @@ pub fn get_commands() -> Command {
                 .arg_required_else_help(true)
                 .arg(
                     arg!(-m --message <COMMIT_MSG> "The commit message")
+                        .required(true)
+                        .value_parser(value_parser!(String)),
+                )
+                .arg(
+                    arg!(--author <AUTHOR> "Override the author, e.g. 'Name <email>'")
+                        .value_parser(value_parser!(String)),
+                )
+                .arg(
+                    arg!(--date <DATE> "Override the author date")
                         .value_parser(value_parser!(String)),
                 ),
         )
+        // branch command
+        .subcommand(
+            Command::new("branch")
+                .about("list, create or delete branches")
+                .arg(
+                    arg!([BRANCH_NAME] "name of the branch to create or delete")
+                        .value_parser(value_parser!(String)),
+                )
+                .arg(
+                    Arg::new("delete")
+                        .short('d')
+                        .long("delete")
+                        .help("Delete a fully merged branch")
+                        .requires("BRANCH_NAME")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("force_delete")
+                        .short('D')
+                        .help("Delete a branch even if it is not merged")
+                        .requires("BRANCH_NAME")
+                        .conflicts_with("delete")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        // log command
+        .subcommand(
+            Command::new("log")
+                .about("shows the commit history starting from HEAD")
+                .arg(
+                    arg!([PATH]..."only show commits changing these paths")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("oneline")
+                        .long("oneline")
+                        .help("Show each commit on a single line")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(-n --"max-count" <COUNT> "Limit the number of commits to show")
+                        .value_parser(value_parser!(usize)),
+                ),
+        )
+        // config command
+        .subcommand(
+            Command::new("config")
+                .about("get and set repository or global options")
+                .arg_required_else_help(true)
+                .arg(arg!([KEY] "name of the option, e.g. user.name"))
+                .arg(arg!([VALUE] "value to set the option to").requires("KEY"))
+                .arg(
+                    Arg::new("list")
+                        .short('l')
+                        .long("list")
+                        .help("List all options with their values")
+                        .conflicts_with_all(["KEY", "unset"])
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("unset")
+                        .long("unset")
+                        .help("Remove the option")
+                        .requires("KEY")
+                        .conflicts_with("VALUE")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("show-origin")
+                        .long("show-origin")
+                        .help("Show the level each listed option comes from")
+                        .requires("list")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("local")
+                        .long("local")
+                        .help("Use the repository config (.rit/config)")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("global")
+                        .long("global")
+                        .help("Use the user config (~/.ritconfig)")
+                        .conflicts_with("local")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("system")
+                        .long("system")
+                        .help("Use the system config (/etc/ritconfig)")
+                        .conflicts_with_all(["local", "global"])
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        // checkout command
+        .subcommand(
+            Command::new("checkout")
+                .about("switch the working tree to a branch or commit")
+                .arg_required_else_help(true)
+                .arg(
+                    arg!(<TARGET> "branch name or commit hash").value_parser(value_parser!(String)),
+                )
+                .arg(
+                    Arg::new("new_branch")
+                        .short('b')
+                        .help("Create TARGET as a new branch at HEAD and switch to it")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("force")
+                        .short('f')
+                        .long("force")
+                        .help("Discard local changes")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        // switch command
+        .subcommand(
+            Command::new("switch")
+                .about("switch the working tree to a branch")
+                .arg_required_else_help(true)
+                .arg(arg!(<BRANCH> "branch to switch to").value_parser(value_parser!(String)))
+                .arg(
+                    Arg::new("create")
+                        .short('c')
+                        .long("create")
+                        .help("Create BRANCH at HEAD and switch to it")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("detach")
+                        .long("detach")
+                        .help("Switch to a commit with a detached HEAD")
+                        .conflicts_with("create")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("force")
+                        .short('f')
+                        .long("force")
+                        .help("Discard local changes")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        // rm command
+        .subcommand(
+            Command::new("rm")
+                .about("remove files from the working tree and from rit memory")
+                .arg_required_else_help(true)
+                .arg(arg!(<PATH>..."paths to remove").value_parser(value_parser!(PathBuf)))
+                .arg(
+                    Arg::new("cached")
+                        .long("cached")
+                        .help("Only remove from rit memory, keep the working file")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("recursive")
+                        .short('r')
+                        .help("Allow removing directories recursively")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("force")
+                        .short('f')
+                        .long("force")
+                        .help("Remove files even if they have uncommitted changes")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        // gc command
+        .subcommand(
+            Command::new("gc")
+                .visible_alias("prune")
+                .about("remove objects no branch, HEAD or INDEX refers to")
+                .arg(
+                    Arg::new("dry-run")
+                        .short('n')
+                        .long("dry-run")
+                        .help("Only report what would be removed")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--prune <EXPIRE> "Only remove objects older than this, e.g. 2.weeks.ago or now")
+                        .value_parser(value_parser!(String)),
+                ),
+        )
+        // fsck command
+        .subcommand(Command::new("fsck").about("verify the objects, refs and INDEX of the repo"))
+        // repack command
+        .subcommand(
+            Command::new("repack")
+                .about("move loose objects into a single delta compressed pack"),
+        )
+        // migrate command
+        .subcommand(
+            Command::new("migrate").about("rewrite the objects, refs and INDEX of a repository created by an older rit"),
+        )
+        // diff command
+        .subcommand(
+            Command::new("diff")
+                .about("show changes between the working tree, INDEX and commits")
+                .arg(
+                    arg!([REVISION]..."one commit to compare with, or two to compare")
+                        .value_parser(value_parser!(String))
+                        .num_args(0..=2),
+                )
+                .arg(
+                    Arg::new("staged")
+                        .long("staged")
+                        .visible_alias("cached")
+                        .help("Compare the INDEX with HEAD instead of the working tree")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!([PATH]..."only show changes of these paths")
+                        .value_parser(value_parser!(PathBuf))
+                        .last(true),
+                )
+                .arg(
+                    arg!(-U --unified <LINES> "Number of context lines")
+                        .value_parser(value_parser!(usize))
+                        .default_value("3"),
+                ),
+        )
     // ...
 }
@@ -1,51 +1,261 @@
 use crate::{
     cli::commands,
-    ops::{add::add_rit, commit::commit_rit, init::init_rit, status::status_rit},
+    models::{
+        configmodels::ConfigLevel,
+        errormodels::{RitError, RitResult},
+        statusmodels::StatusFormat,
+    },
+    ops::{
+        add::print_add_outcomes,
+        branch::{create_branch_rit, delete_branch_rit, list_branches_rit},
+        checkout::{checkout_rit, DetachMode},
+        config::{config_get_rit, config_list_rit, config_set_rit, config_unset_rit},
+        diff::diff_rit,
+        fsck::fsck_rit,
+        gc::gc_rit,
+        init::init_rit,
+        log::print_log,
+        migrate::migrate_rit,
+        repack::repack_rit,
+        rm::rm_rit,
+        status::print_status,
+    },
+    repository::{Repository, RIT_DIR_NAME},
     utils::ioutils::get_all_paths,
 };
-use std::path::PathBuf;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process,
+};
 
-pub fn exec_cli() {
-    let matches = commands::get_commands().get_matches();
+/// Finds the repository for the command, a new one for init.
+/// Returns it with the dir the command runs in, i.e. the
+/// current dir or the one given with -C.
+fn find_repository(matches: &clap::ArgMatches) -> RitResult<(PathBuf, Option<Repository>)> {
+    let mut cwd = env::current_dir()?;
+    if let Some(dir) = matches.get_one::<PathBuf>("directory") {
+        cwd = cwd.join(dir).canonicalize()?;
+    }
+
+    let rit_dir = matches
+        .get_one::<PathBuf>("rit-dir")
+        .cloned()
+        .or_else(|| env::var_os("RIT_DIR").map(PathBuf::from));
+
+    let repo = match matches.subcommand_name() {
+        Some("init") => Repository::new(
+            cwd.clone(),
+            cwd.join(rit_dir.unwrap_or_else(|| PathBuf::from(RIT_DIR_NAME))),
+        ),
+        name => match Repository::discover(&cwd, rit_dir.as_deref()) {
+            Ok(repo) => repo,
+            // config works outside of a repository for --global and --system
+            Err(RitError::NotARepository(_)) if name == Some("config") => return Ok((cwd, None)),
+            Err(e) => return Err(e),
+        },
+    };
+
+    Ok((cwd, Some(repo)))
+}
 
+/// Maps the PATH arguments, relative to where rit was started,
+/// to paths relative to the root of the work tree.
+fn get_path_args(
+    sub_matches: &clap::ArgMatches,
+    repo: Option<&Repository>,
+    cwd: &Path,
+) -> RitResult<Vec<PathBuf>> {
+    let paths = sub_matches
+        .get_many::<PathBuf>("PATH")
+        .into_iter()
+        .flatten();
+
+    match repo {
+        Some(repo) => paths.map(|p| repo.to_work_tree_path(cwd, p)).collect(),
+        None => Ok(paths.cloned().collect()),
+    }
+}
+
+/// The repository of a command, all but config need one.
+fn expect_repository(repo: Option<&Repository>) -> &Repository {
+    repo.expect("only config runs outside of a repository")
+}
+
+fn run_command(matches: &clap::ArgMatches, cwd: &Path, repo: Option<&Repository>) -> RitResult<()> {
     match matches.subcommand() {
         Some(("add", sub_matches)) => {
             let mut add_all = false;
             if let Some(a) = sub_matches.get_one::<bool>("all") {
                 add_all = *a
             }
-            let paths;
-            match add_all {
-                true => paths = get_all_paths(),
-                false => {
-                    paths = sub_matches
-                        .get_many::<PathBuf>("PATH")
-                        .into_iter()
-                        .flatten()
-                        .cloned()
-                        .collect::<Vec<_>>();
+            let paths = match add_all {
+                true => get_all_paths(),
+                false => get_path_args(sub_matches, repo, cwd)?,
+            };
+
+            let outcomes = expect_repository(repo).add(&paths)?;
+            print_add_outcomes(&outcomes)?;
+            println!("files added");
+            Ok(())
+        }
+
+        Some(("init", _)) => {
+            init_rit()?;
+            println!("rit initalized!");
+            Ok(())
+        }
+
+        Some(("status", sub_matches)) => {
+            let format = if sub_matches.get_flag("porcelain") {
+                StatusFormat::Porcelain
+            } else if sub_matches.get_flag("json") {
+                StatusFormat::Json
+            } else {
+                StatusFormat::Long
+            };
+
+            let status = expect_repository(repo).status()?;
+            print_status(&status, format)
+        }
+
+        Some(("commit", sub_matches)) => {
+            let commit_msg = sub_matches.get_one::<String>("message").unwrap().trim();
+
+            let author = sub_matches.get_one::<String>("author");
+            let date = sub_matches.get_one::<String>("date");
+
+            let commit_hash = expect_repository(repo).commit(
+                commit_msg,
+                author.map(|a| a.as_str()),
+                date.map(|d| d.as_str()),
+            )?;
+            if commit_hash.is_none() {
+                println!("** Everything Up-to-date **");
+            }
+            Ok(())
+        }
+
+        Some(("branch", sub_matches)) => {
+            let delete = sub_matches.get_flag("delete");
+            let force_delete = sub_matches.get_flag("force_delete");
+
+            match sub_matches.get_one::<String>("BRANCH_NAME") {
+                Some(name) if delete || force_delete => delete_branch_rit(name, force_delete),
+                Some(name) => create_branch_rit(name),
+                None => list_branches_rit(),
+            }
+        }
+
+        Some(("log", sub_matches)) => {
+            let oneline = sub_matches.get_flag("oneline");
+            let max_count = sub_matches.get_one::<usize>("max-count").copied();
+            let paths = get_path_args(sub_matches, repo, cwd)?;
+
+            let commits = expect_repository(repo).log(max_count, &paths)?;
+            print_log(&commits, oneline)
+        }
+
+        Some(("config", sub_matches)) => {
+            let level = if sub_matches.get_flag("system") {
+                Some(ConfigLevel::System)
+            } else if sub_matches.get_flag("global") {
+                Some(ConfigLevel::Global)
+            } else if sub_matches.get_flag("local") {
+                Some(ConfigLevel::Local)
+            } else {
+                None
+            };
+
+            let key = sub_matches.get_one::<String>("KEY");
+            let value = sub_matches.get_one::<String>("VALUE");
+
+            match (key, value) {
+                _ if sub_matches.get_flag("list") => {
+                    config_list_rit(level, sub_matches.get_flag("show-origin"))
                 }
+                (Some(k), _) if sub_matches.get_flag("unset") => config_unset_rit(k, level),
+                (Some(k), Some(v)) => config_set_rit(k, v, level),
+                (Some(k), None) => config_get_rit(k, level),
+                (None, _) => Ok(()),
             }
+        }
+
+        Some(("checkout", sub_matches)) => {
+            let target = sub_matches.get_one::<String>("TARGET").unwrap();
+            let new_branch = sub_matches.get_flag("new_branch");
+            let force = sub_matches.get_flag("force");
+
+            checkout_rit(target, new_branch, force, DetachMode::NonBranch)
+        }
 
-            match add_rit(paths) {
-                Ok(_) => println!("files added"),
-                Err(e) => eprintln!("{}", e.to_string()),
+        Some(("switch", sub_matches)) => {
+            let branch = sub_matches.get_one::<String>("BRANCH").unwrap();
+            let create = sub_matches.get_flag("create");
+            let detach = sub_matches.get_flag("detach");
+            let force = sub_matches.get_flag("force");
+
+            let detach = match detach {
+                true => DetachMode::Always,
+                false => DetachMode::Never,
             };
+
+            checkout_rit(branch, create, force, detach)
         }
 
-        Some(("init", _)) => match init_rit() {
-            Ok(_) => println!("rit initalized!"),
-            Err(e) => eprintln!("{:?}", e.to_string()),
-        },
+        Some(("rm", sub_matches)) => {
+            let paths = get_path_args(sub_matches, repo, cwd)?;
+            let cached = sub_matches.get_flag("cached");
+            let recursive = sub_matches.get_flag("recursive");
+            let force = sub_matches.get_flag("force");
 
-        Some(("status", _)) => status_rit(),
+            rm_rit(paths, cached, recursive, force)
+        }
 
-        Some(("commit", sub_matches)) => {
-            let commit_msg = sub_matches.get_one::<String>("message").unwrap().trim();
+        Some(("gc", sub_matches)) => {
+            let dry_run = sub_matches.get_flag("dry-run");
+            let prune_expire = sub_matches.get_one::<String>("prune");
+
+            gc_rit(dry_run, prune_expire.map(|p| p.as_str()))
+        }
+
+        Some(("fsck", _)) => fsck_rit(),
+
+        Some(("repack", _)) => repack_rit(),
+
+        Some(("migrate", _)) => migrate_rit(),
+
+        Some(("diff", sub_matches)) => {
+            let staged = sub_matches.get_flag("staged");
+            let context = *sub_matches.get_one::<usize>("unified").unwrap();
+            let revisions = sub_matches
+                .get_many::<String>("REVISION")
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>();
 
-            commit_rit(commit_msg);
+            let paths = get_path_args(sub_matches, repo, cwd)?;
+
+            diff_rit(staged, revisions, paths, context)
         }
 
         _ => unreachable!(),
     }
 }
+
+/// Runs the command and exits with the code of its error, see
+/// `RitError::exit_code`.
+pub fn exec_cli() {
+    let matches = commands::get_commands().get_matches();
+
+    let res = find_repository(&matches).and_then(|(cwd, repo)| {
+        let _active = repo.as_ref().map(|r| r.activate());
+        run_command(&matches, &cwd, repo.as_ref())
+    });
+
+    if let Err(e) = res {
+        eprintln!("{}", e);
+        process::exit(e.exit_code());
+    }
+}
@@ -1,4 +1,5 @@
 pub mod cli;
 pub mod models;
 pub mod ops;
+pub mod repository;
 pub mod utils;
@@ -0,0 +1,100 @@
+use std::{fmt, io};
+
+/// Parsed form of a commit object written by `commit::prepare_commit_content`.
+#[derive(Debug)]
+pub struct Commit {
+    pub tree_hash: Vec<u8>,
+    pub parent_hash: Option<Vec<u8>>,
+    pub author: String,
+    pub committer: String,
+    pub message: String,
+}
+
+impl Commit {
+    pub fn from_bytes(b: &[u8]) -> io::Result<Self> {
+        // 32 bytes tree hash + 1 byte parent flag + 32 bytes parent hash
+        if b.len() < 65 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "commit object is too short",
+            ));
+        }
+
+        let tree_hash = b[..32].to_vec();
+        let parent_hash = match b[32] {
+            0 => None,
+            _ => Some(b[33..65].to_vec()),
+        };
+
+        let mut lines = b[65..].splitn(3, |c| *c == b'\n');
+        let author = String::from_utf8_lossy(lines.next().unwrap_or_default()).into_owned();
+        let committer = String::from_utf8_lossy(lines.next().unwrap_or_default()).into_owned();
+        // the remainder starts with the empty separator line
+        let message = match lines.next() {
+            Some(rest) => {
+                String::from_utf8_lossy(rest.strip_prefix(b"\n").unwrap_or(rest)).into_owned()
+            }
+            None => String::new(),
+        };
+
+        Ok(Self {
+            tree_hash,
+            parent_hash,
+            author,
+            committer,
+            message,
+        })
+    }
+}
+
+/// Author or committer line of a commit:
+/// <NAME> <EMAIL> [<DATE_SEC> <TIMEZONE>]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub name: String,
+    pub email: String,
+    pub timestamp: Option<i64>,
+    pub timezone: Option<String>,
+}
+
+impl Signature {
+    pub fn parse(line: &str) -> Self {
+        let mut tokens: Vec<&str> = line.split_whitespace().collect();
+
+        let mut timestamp = None;
+        let mut timezone = None;
+        if tokens.len() >= 2 {
+            let tz = tokens[tokens.len() - 1];
+            let is_tz = tz.len() == 5
+                && (tz.starts_with('+') || tz.starts_with('-'))
+                && tz[1..].chars().all(|c| c.is_ascii_digit());
+            if let (true, Ok(sec)) = (is_tz, tokens[tokens.len() - 2].parse::<i64>()) {
+                timestamp = Some(sec);
+                timezone = Some(tz.to_string());
+                tokens.truncate(tokens.len() - 2);
+            }
+        }
+
+        let email = tokens
+            .pop()
+            .map(|e| e.trim_start_matches('<').trim_end_matches('>').to_string())
+            .unwrap_or_default();
+
+        Self {
+            name: tokens.join(" "),
+            email,
+            timestamp,
+            timezone,
+        }
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} <{}>", self.name, self.email)?;
+        if let (Some(ts), Some(tz)) = (self.timestamp, &self.timezone) {
+            write!(f, " {} {}", ts, tz)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+use std::fmt;
+
+/// Config files are read from the least to the most specific
+/// level, so a value in a later level overrides an earlier one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigLevel {
+    System,
+    Global,
+    Local,
+}
+
+impl ConfigLevel {
+    pub const ALL: [ConfigLevel; 3] =
+        [ConfigLevel::System, ConfigLevel::Global, ConfigLevel::Local];
+}
+
+impl fmt::Display for ConfigLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigLevel::System => write!(f, "system"),
+            ConfigLevel::Global => write!(f, "global"),
+            ConfigLevel::Local => write!(f, "local"),
+        }
+    }
+}
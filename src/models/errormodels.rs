@@ -0,0 +1,159 @@
+use std::{
+    error, fmt,
+    io::{self, ErrorKind},
+    path::PathBuf,
+};
+
+use crate::models::indexmodels::IndexError;
+
+pub type RitResult<T> = Result<T, RitError>;
+
+/// Errors of the rit operations. Utils return `io::Result` and carry
+/// these inside an `io::Error`, converting the `io::Error` back with
+/// `?` recovers the variant.
+#[derive(Debug)]
+pub enum RitError {
+    /// neither the dir nor any of its parents holds a repository
+    NotARepository(PathBuf),
+    /// bad arguments, e.g. a path outside of the work tree
+    InvalidInput(String),
+    /// a revision that is neither a branch nor a commit
+    UnknownRevision(String),
+    MissingObject(String),
+    CorruptObject {
+        hash: String,
+        reason: String,
+    },
+    CorruptIndex(IndexError),
+    /// problems found by fsck
+    CorruptRepository(String),
+    /// the lock file of a ref or INDEX already exists
+    LockContention(PathBuf),
+    /// local changes to these paths would be overwritten
+    MergeConflict(Vec<String>),
+    /// something named by the user does not exist, e.g. a branch
+    NotFound(String),
+    /// something the user wants to create exists already
+    AlreadyExists(String),
+    Io(io::Error),
+}
+
+impl RitError {
+    /// Exit code of the CLI, distinct for every kind of error
+    /// so scripts can react to them.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RitError::Io(_) => 1,
+            RitError::InvalidInput(_) => 2,
+            RitError::NotARepository(_) => 3,
+            RitError::UnknownRevision(_) => 4,
+            RitError::MissingObject(_) => 5,
+            RitError::CorruptObject { .. } => 6,
+            RitError::CorruptIndex(_) => 7,
+            RitError::CorruptRepository(_) => 8,
+            RitError::LockContention(_) => 9,
+            RitError::MergeConflict(_) => 10,
+            RitError::NotFound(_) => 11,
+            RitError::AlreadyExists(_) => 12,
+        }
+    }
+
+    fn io_kind(&self) -> ErrorKind {
+        match self {
+            RitError::Io(e) => e.kind(),
+            RitError::InvalidInput(_) => ErrorKind::InvalidInput,
+            RitError::NotARepository(_)
+            | RitError::UnknownRevision(_)
+            | RitError::MissingObject(_)
+            | RitError::NotFound(_) => ErrorKind::NotFound,
+            RitError::AlreadyExists(_) => ErrorKind::AlreadyExists,
+            RitError::CorruptObject { .. }
+            | RitError::CorruptIndex(_)
+            | RitError::CorruptRepository(_) => ErrorKind::InvalidData,
+            RitError::LockContention(_) => ErrorKind::ResourceBusy,
+            RitError::MergeConflict(_) => ErrorKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for RitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RitError::NotARepository(path) => write!(
+                f,
+                "not a rit repository (or any of the parent directories): {}\n\n\
+                 run this command:\n> rit init",
+                path.display()
+            ),
+            RitError::InvalidInput(msg)
+            | RitError::CorruptRepository(msg)
+            | RitError::NotFound(msg)
+            | RitError::AlreadyExists(msg) => write!(f, "{}", msg),
+            RitError::UnknownRevision(rev) => {
+                write!(f, "'{}' is neither a branch nor a commit", rev)
+            }
+            RitError::MissingObject(hash) => write!(f, "object {} not found", hash),
+            RitError::CorruptObject { hash, reason } => {
+                write!(f, "corrupt object {}: {}", hash, reason)
+            }
+            RitError::CorruptIndex(e) => write!(f, "{}", e),
+            RitError::LockContention(lock_path) => write!(
+                f,
+                "unable to create '{}': file exists\n\n\
+                 another rit process seems to be running in this repository.\n\
+                 if it crashed, remove the file and try again",
+                lock_path.display()
+            ),
+            RitError::MergeConflict(paths) => write!(
+                f,
+                "your local changes to the following files would be overwritten:\n\t{}\n\
+                 commit them or use --force to discard them",
+                paths.join("\n\t")
+            ),
+            RitError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for RitError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RitError::CorruptIndex(e) => Some(e),
+            RitError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RitError {
+    fn from(e: io::Error) -> Self {
+        let carries_typed = e
+            .get_ref()
+            .is_some_and(|inner| inner.is::<RitError>() || inner.is::<IndexError>());
+        if !carries_typed {
+            return match e.kind() {
+                ErrorKind::InvalidInput => RitError::InvalidInput(e.to_string()),
+                _ => RitError::Io(e),
+            };
+        }
+
+        let kind = e.kind();
+        let inner = e.into_inner().expect("checked above");
+        match inner.downcast::<RitError>() {
+            Ok(rit) => *rit,
+            Err(inner) => match inner.downcast::<IndexError>() {
+                Ok(index) => RitError::CorruptIndex(*index),
+                Err(inner) => RitError::Io(io::Error::new(kind, inner)),
+            },
+        }
+    }
+}
+
+impl From<RitError> for io::Error {
+    fn from(e: RitError) -> Self {
+        match e {
+            RitError::Io(e) => e,
+            e => io::Error::new(e.io_kind(), e),
+        }
+    }
+}
@@ -1,3 +1,47 @@
+use std::fs::Metadata;
+use std::os::unix::fs::MetadataExt;
+use std::{error, fmt};
+
+pub const INDEX_SIGNATURE: [u8; 4] = *b"DIRC";
+/// Version 4 adds a trailing sha256 of the whole file,
+/// version 3 files (without one) can still be read.
+pub const INDEX_VERSION: u32 = 4;
+pub const INDEX_CHECKSUM_LEN: usize = 32;
+
+/// Why a `.rit/INDEX` could not be parsed. Offsets are in bytes
+/// from the start of the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexError {
+    Truncated { offset: usize, field: &'static str },
+    InvalidSignature,
+    UnsupportedVersion(u32),
+    InvalidPath { offset: usize },
+    TrailingData { offset: usize },
+    ChecksumMismatch,
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "corrupt INDEX: ")?;
+        match self {
+            IndexError::Truncated { offset, field } => {
+                write!(f, "file ends in the {} at byte {}", field, offset)
+            }
+            IndexError::InvalidSignature => write!(f, "invalid signature"),
+            IndexError::UnsupportedVersion(v) => write!(f, "unsupported version {}", v),
+            IndexError::InvalidPath { offset } => {
+                write!(f, "invalid file path at byte {}", offset)
+            }
+            IndexError::TrailingData { offset } => {
+                write!(f, "unexpected data after the entries at byte {}", offset)
+            }
+            IndexError::ChecksumMismatch => write!(f, "checksum mismatch"),
+        }
+    }
+}
+
+impl error::Error for IndexError {}
+
 #[derive(Debug)]
 pub struct IndexHeader {
     num_entries: u32,   // 3
@@ -51,3 +95,38 @@ pub struct IndexEntry {
     pub file_path_len: u32,
     pub file_path: String, // Relative path of file from root, stored as null-terminated str
 }
+
+impl IndexEntry {
+    /// Builds an entry for `file_path` from the file's current metadata.
+    pub fn from_metadata(file_path: String, sha_hash: Vec<u8>, md: &Metadata) -> Self {
+        Self {
+            ctime: (md.ctime() as u32, md.ctime_nsec() as u32),
+            mtime: (md.mtime() as u32, md.mtime_nsec() as u32),
+            device: md.dev() as u32,
+            inode: md.ino() as u32,
+            mode: md.mode(),
+            size: md.size() as u32,
+            sha_hash,
+            file_path_len: file_path.len() as u32,
+            file_path,
+        }
+    }
+
+    /// Whether the cached stat data still describes the file,
+    /// in which case its content is assumed to be unchanged.
+    pub fn matches_metadata(&self, md: &Metadata) -> bool {
+        self.ctime == (md.ctime() as u32, md.ctime_nsec() as u32)
+            && self.mtime == (md.mtime() as u32, md.mtime_nsec() as u32)
+            && self.device == md.dev() as u32
+            && self.inode == md.ino() as u32
+            && self.mode == md.mode()
+            && self.size == md.size() as u32
+    }
+
+    /// A file changed in the same instant the INDEX was written can
+    /// be modified again without its mtime moving, so stat data of
+    /// entries not older than the INDEX itself cannot be trusted.
+    pub fn is_racy(&self, index_mtime: (u32, u32)) -> bool {
+        self.mtime >= index_mtime
+    }
+}
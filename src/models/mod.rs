@@ -1 +1,8 @@
+pub mod commitmodels;
+pub mod configmodels;
+pub mod errormodels;
 pub mod indexmodels;
+pub mod objectmodels;
+pub mod refmodels;
+pub mod statusmodels;
+pub mod treemodels;
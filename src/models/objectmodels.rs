@@ -0,0 +1,115 @@
+use std::{fmt, io};
+
+use crate::models::{
+    commitmodels::Commit,
+    treemodels::{parse_tree, TreeEntry},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Blob,
+    Tree,
+    Commit,
+}
+
+impl ObjectKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectKind::Blob => "blob",
+            ObjectKind::Tree => "tree",
+            ObjectKind::Commit => "commit",
+        }
+    }
+
+    pub fn from_bytes(b: &[u8]) -> Option<Self> {
+        match b {
+            b"blob" => Some(ObjectKind::Blob),
+            b"tree" => Some(ObjectKind::Tree),
+            b"commit" => Some(ObjectKind::Commit),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ObjectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A parsed object from .rit/objects
+#[derive(Debug)]
+pub enum Object {
+    Blob(Vec<u8>),
+    Tree(Vec<TreeEntry>),
+    Commit(Commit),
+}
+
+impl Object {
+    pub fn kind(&self) -> ObjectKind {
+        match self {
+            Object::Blob(_) => ObjectKind::Blob,
+            Object::Tree(_) => ObjectKind::Tree,
+            Object::Commit(_) => ObjectKind::Commit,
+        }
+    }
+
+    /// Parses a stored object, envelope included.
+    pub fn from_bytes(b: &[u8]) -> io::Result<Self> {
+        let (kind, content) = decode_object(b)?;
+        Ok(match kind {
+            ObjectKind::Blob => Object::Blob(content.to_vec()),
+            ObjectKind::Tree => Object::Tree(parse_tree(content)?),
+            ObjectKind::Commit => Object::Commit(Commit::from_bytes(content)?),
+        })
+    }
+}
+
+/// Every object is stored (and hashed) with an envelope
+/// so the kind of an object is known from its bytes:
+/// - <KIND> (blob, tree or commit)
+/// - one space
+/// - <CONTENT_LEN> in decimal
+/// - one null byte
+/// - <CONTENT> (CONTENT_LEN bytes)
+pub fn encode_object(kind: ObjectKind, content: &[u8]) -> Vec<u8> {
+    let mut object = format!("{} {}\0", kind, content.len()).into_bytes();
+    object.extend_from_slice(content);
+    object
+}
+
+/// Splits a stored object into its kind and content.
+pub fn decode_object(b: &[u8]) -> io::Result<(ObjectKind, &[u8])> {
+    let invalid = |reason: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("corrupt object: {}", reason),
+        )
+    };
+
+    // the longest header is "commit 18446744073709551615\0"
+    let header_end = b
+        .iter()
+        .take(32)
+        .position(|c| *c == 0)
+        .ok_or_else(|| invalid("missing object header"))?;
+    let header = &b[..header_end];
+
+    let space = header
+        .iter()
+        .position(|c| *c == b' ')
+        .ok_or_else(|| invalid("malformed object header"))?;
+    let kind =
+        ObjectKind::from_bytes(&header[..space]).ok_or_else(|| invalid("unknown object kind"))?;
+    let len: usize = std::str::from_utf8(&header[space + 1..])
+        .ok()
+        .and_then(|l| l.parse().ok())
+        .ok_or_else(|| invalid("malformed object length"))?;
+
+    let content = &b[header_end + 1..];
+    if content.len() != len {
+        return Err(invalid("object length does not match its header"));
+    }
+
+    Ok((kind, content))
+}
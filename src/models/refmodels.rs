@@ -0,0 +1,7 @@
+/// What `.rit/HEAD` points to: either a branch under
+/// refs/heads (symbolic ref) or directly a commit (detached).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Head {
+    Branch(String),
+    Detached(Vec<u8>),
+}
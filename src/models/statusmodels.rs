@@ -0,0 +1,133 @@
+use std::{collections::BTreeMap, fmt};
+
+/// How a path differs between two of HEAD, INDEX and the working tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    New,
+    Modified,
+    Deleted,
+    /// Same content under a new path, `from` is the old path.
+    Renamed {
+        from: String,
+    },
+}
+
+impl ChangeKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChangeKind::New => "new file",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Deleted => "deleted",
+            ChangeKind::Renamed { .. } => "renamed",
+        }
+    }
+
+    /// One letter code as used by `status --porcelain`.
+    pub fn code(&self) -> char {
+        match self {
+            ChangeKind::New => 'A',
+            ChangeKind::Modified => 'M',
+            ChangeKind::Deleted => 'D',
+            ChangeKind::Renamed { .. } => 'R',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub kind: ChangeKind,
+    pub file_path: String,
+}
+
+impl fmt::Display for FileChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = format!("{}:", self.kind.label());
+        match &self.kind {
+            ChangeKind::Renamed { from } => {
+                write!(f, "{:<12}{} -> {}", label, from, self.file_path)
+            }
+            _ => write!(f, "{:<12}{}", label, self.file_path),
+        }
+    }
+}
+
+/// Result of comparing HEAD, INDEX and the working tree:
+/// - `staged`: INDEX vs the tree of HEAD ("Changes to be committed")
+/// - `unstaged`: working tree vs INDEX ("Changes not staged")
+/// - `untracked`: files in the working tree that are not in INDEX
+#[derive(Debug, Default)]
+pub struct Status {
+    pub staged: Vec<FileChange>,
+    pub unstaged: Vec<FileChange>,
+    pub untracked: Vec<String>,
+}
+
+/// The state of one path in INDEX (vs HEAD) and in the working tree
+/// (vs INDEX), `None` when unchanged. Untracked paths have neither.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusEntry<'a> {
+    pub file_path: &'a str,
+    pub index: Option<&'a ChangeKind>,
+    pub worktree: Option<&'a ChangeKind>,
+}
+
+impl StatusEntry<'_> {
+    pub fn is_untracked(&self) -> bool {
+        self.index.is_none() && self.worktree.is_none()
+    }
+
+    /// The two column "XY" code of `status --porcelain`:
+    /// X for INDEX, Y for the working tree, "??" when untracked.
+    pub fn xy(&self) -> String {
+        if self.is_untracked() {
+            return "??".to_string();
+        }
+        let code = |kind: Option<&ChangeKind>| kind.map(|k| k.code()).unwrap_or(' ');
+        format!("{}{}", code(self.index), code(self.worktree))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusFormat {
+    Long,
+    /// stable "XY <path>" lines
+    Porcelain,
+    Json,
+}
+
+impl Status {
+    pub fn is_clean(&self) -> bool {
+        self.staged.is_empty() && self.unstaged.is_empty() && self.untracked.is_empty()
+    }
+
+    /// One entry per changed path sorted by path, untracked paths last.
+    pub fn entries(&self) -> Vec<StatusEntry<'_>> {
+        let mut tracked: BTreeMap<&str, StatusEntry> = BTreeMap::new();
+        for (change, staged) in self
+            .staged
+            .iter()
+            .map(|c| (c, true))
+            .chain(self.unstaged.iter().map(|c| (c, false)))
+        {
+            let entry = tracked
+                .entry(&change.file_path)
+                .or_insert_with(|| StatusEntry {
+                    file_path: &change.file_path,
+                    index: None,
+                    worktree: None,
+                });
+            match staged {
+                true => entry.index = Some(&change.kind),
+                false => entry.worktree = Some(&change.kind),
+            }
+        }
+
+        let mut entries: Vec<StatusEntry> = tracked.into_values().collect();
+        entries.extend(self.untracked.iter().map(|file_path| StatusEntry {
+            file_path,
+            index: None,
+            worktree: None,
+        }));
+        entries
+    }
+}
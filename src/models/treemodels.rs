@@ -0,0 +1,94 @@
+use std::io;
+
+/// Mode of a tree entry pointing to the tree of a subdirectory.
+pub const TREE_MODE: u32 = 0o040000;
+
+/// One record of a tree object written by `commit::write_tree_file`:
+/// - <MODE> (4 bytes)
+/// - <FILE_PATH_LEN> (4 bytes)
+/// - <FILE_PATH> (variable bytes)
+/// - <HASH> (32 bytes)
+/// - zero padding up to an 8-byte boundary
+///
+/// <FILE_PATH> is relative to the tree holding the entry, i.e. a single
+/// path component. Trees written before subdirectories got their own
+/// tree objects hold the whole "./dir/file" path instead.
+#[derive(Debug, Clone)]
+pub struct TreeEntry {
+    pub mode: u32,
+    pub file_path: String,
+    pub sha_hash: Vec<u8>,
+}
+
+impl TreeEntry {
+    pub fn is_tree(&self) -> bool {
+        self.mode == TREE_MODE
+    }
+}
+
+fn invalid_tree(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("corrupt tree object: {}", reason),
+    )
+}
+
+pub fn parse_tree(tree_content: &[u8]) -> io::Result<Vec<TreeEntry>> {
+    let mut entries = vec![];
+    let mut pos: usize = 0;
+
+    while pos + 8 <= tree_content.len() {
+        let mode = u32::from_be_bytes(tree_content[pos..pos + 4].try_into().unwrap());
+        let file_path_len =
+            u32::from_be_bytes(tree_content[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        pos += 8;
+
+        let path_end = pos + file_path_len;
+        if path_end + 32 > tree_content.len() {
+            return Err(invalid_tree("entry exceeds object size"));
+        }
+        let file_path = String::from_utf8_lossy(&tree_content[pos..path_end]).into_owned();
+        pos = path_end;
+
+        let sha_hash = tree_content[pos..pos + 32].to_vec();
+        pos += 32;
+
+        entries.push(TreeEntry {
+            mode,
+            file_path,
+            sha_hash,
+        });
+
+        pos = (pos + 7) & !7;
+    }
+
+    if pos < tree_content.len() {
+        return Err(invalid_tree("trailing bytes"));
+    }
+
+    Ok(entries)
+}
+
+pub fn serialize_tree(entries: &[TreeEntry]) -> Vec<u8> {
+    let mut tree_content: Vec<u8> = Vec::new();
+
+    for te in entries.iter() {
+        // 4 bytes for mode
+        tree_content.extend_from_slice(&te.mode.to_be_bytes());
+        // 4 bytes for file_path_len
+        tree_content.extend_from_slice(&(te.file_path.len() as u32).to_be_bytes());
+        // unknows bytes for file_path (inferred from the previous 4 bytes)
+        tree_content.extend_from_slice(te.file_path.as_bytes());
+        // 32 bytes for sha hash
+        tree_content.extend_from_slice(&te.sha_hash);
+
+        // Adding extra 0 if necessary for 8-byte alignment
+        let offset = tree_content.len() % 8;
+        if offset > 0 {
+            let padding = 8 - offset;
+            tree_content.extend_from_slice(&vec![0; padding]);
+        }
+    }
+
+    tree_content
+}
@@ -6,46 +6,47 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::models::indexmodels::{IndexEntry, IndexHeader};
-use crate::utils::hashutils::get_hash_from_file;
+use crate::models::{
+    errormodels::{RitError, RitResult},
+    indexmodels::{IndexEntry, IndexHeader, INDEX_SIGNATURE, INDEX_VERSION},
+    objectmodels::{encode_object, ObjectKind},
+};
+use crate::repository;
 use crate::utils::ioutils::{
-    delete_file_hash, get_objects_path, read_index, save_file_hash, write_index,
+    get_objects_path, lock_index, read_index, save_file_hash, write_index_locked,
 };
-
-fn is_path_processable(path: &PathBuf) -> bool {
-    if path.is_dir() {
-        println!("{} is a dir. Choose file please.", path.display());
-        return false;
-    }
-
-    if !path.exists() {
-        println!("{} does NOT exist; skipping...", path.display());
-        return false;
-    }
-
-    true
+use crate::utils::{hashutils::get_hash_from_file, ignoreutils::IgnoreRules};
+
+/// What `add_paths` did with a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddOutcome {
+    Added,
+    /// already in INDEX with the same content
+    Unchanged,
+    /// new and matched by an ignore rule
+    Ignored,
+    IsDir,
+    NotFound,
 }
 
-fn get_file_path_info(path: &PathBuf) -> (String, u32) {
-    let file_path = match !path.to_str().unwrap().starts_with("./") {
+fn get_file_path_info(path: &Path) -> String {
+    match !path.to_str().unwrap().starts_with("./") {
         true => format!("{}", Path::new(".").join(path).to_string_lossy()),
         false => format!("{}", path.to_string_lossy()),
-    };
-
-    let file_path_len = file_path.as_bytes().len() as u32;
-    (file_path, file_path_len)
+    }
 }
 
-/// This should add the series of files requested by user.
+/// Stages the given files, paths are relative to the root of the work tree.
 /// First it checks what files exist if .rit/INDEX exists.
 /// If the required files already added, then it does nothing.
 /// Any new requested files will be added.
-pub fn add_rit(paths: Vec<PathBuf>) -> Result<bool, Box<dyn std::error::Error>> {
+pub fn add_paths(paths: &[PathBuf]) -> RitResult<Vec<(PathBuf, AddOutcome)>> {
     let objects_path = get_objects_path()?;
 
     let mut index_entries: Vec<IndexEntry> = vec![];
-    let mut header = IndexHeader::new(0, 3, *b"DIRC");
+    let mut header = IndexHeader::new(0, INDEX_VERSION, INDEX_SIGNATURE);
     let mut existing_files: HashMap<String, (usize, Vec<u8>)> = HashMap::new();
+    let lock = lock_index()?;
     match read_index() {
         Ok(res) => {
             header = res.0;
@@ -62,79 +63,119 @@ pub fn add_rit(paths: Vec<PathBuf>) -> Result<bool, Box<dyn std::error::Error>>
         }
     }
 
-    let all_paths_len = paths.len();
-    let mut success: usize = 0;
+    let mut ignore_rules = IgnoreRules::load();
+
+    let mut outcomes = vec![];
+    let mut changed = false;
     for path in paths.iter() {
-        if !is_path_processable(path) {
-            continue;
-        }
+        let worktree_path = repository::work_tree_path(path);
+        let outcome = if worktree_path.is_dir() {
+            AddOutcome::IsDir
+        } else if !worktree_path.exists() {
+            AddOutcome::NotFound
+        } else {
+            add_path(
+                path,
+                &worktree_path,
+                &objects_path,
+                &mut ignore_rules,
+                &mut existing_files,
+                &mut index_entries,
+                &mut header,
+            )?
+        };
 
-        let (file_path, file_path_len) = get_file_path_info(path);
-        //
-        let content = fs::read(path)?;
-        let (file_hash, hash_vec) = get_hash_from_file(&content);
-
-        if let Some(info) = existing_files.get(&file_path) {
-            match *info.1 == hash_vec {
-                // No change in the file
-                true => {
-                    header.increment_num_entries();
-                    println!("this file already added.");
-                    continue;
-                }
-                // Means file has been modified, removing it
-                // from the existing items to be added again
-                // with a new hash
-                false => {
-                    let file_hash = hex::encode(info.1.clone());
-                    delete_file_hash(&objects_path, &file_hash)?;
-                    index_entries.remove(info.0);
-                    header.decrement_num_entries();
-                }
-            }
-        }
+        changed |= outcome == AddOutcome::Added;
+        outcomes.push((path.clone(), outcome));
+    }
 
-        match save_file_hash(&file_hash, &objects_path, &content) {
-            Ok(_) => {}
-            Err(e) => {
-                match e.kind() {
-                    io::ErrorKind::AlreadyExists => success += 1,
-                    _ => println!("hash save error: {}", e),
-                }
-                continue;
-            }
+    if changed {
+        write_index_locked(lock, header, index_entries)?;
+    }
+
+    Ok(outcomes)
+}
+
+fn add_path(
+    path: &Path,
+    worktree_path: &Path,
+    objects_path: &Path,
+    ignore_rules: &mut IgnoreRules,
+    existing_files: &mut HashMap<String, (usize, Vec<u8>)>,
+    index_entries: &mut Vec<IndexEntry>,
+    header: &mut IndexHeader,
+) -> io::Result<AddOutcome> {
+    let file_path = get_file_path_info(path);
+
+    // tracked files can still be updated, new ignored ones are skipped
+    if !existing_files.contains_key(&file_path)
+        && ignore_rules.is_path_ignored(file_path.trim_start_matches("./"), false)
+    {
+        return Ok(AddOutcome::Ignored);
+    }
+    //
+    let content = fs::read(worktree_path)?;
+    let object = encode_object(ObjectKind::Blob, &content);
+    let (file_hash, hash_vec) = get_hash_from_file(&object);
+
+    let md = fs::metadata(worktree_path)?;
+
+    // No change in the file. A chmod alone still rewrites
+    // the entry, the mode is part of what gets committed.
+    if let Some((idx, existing_hash)) = existing_files.get(&file_path) {
+        if *existing_hash == hash_vec && index_entries[*idx].mode == md.mode() {
+            return Ok(AddOutcome::Unchanged);
         }
+    }
 
-        let md = match fs::metadata(path) {
-            Ok(m) => m,
-            Err(e) => {
-                println!("reading metadata error: {}", e);
-                continue;
-            }
-        };
+    match save_file_hash(&file_hash, objects_path, &object) {
+        Ok(_) => {}
+        // same content is already stored, it still needs an index entry
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e),
+    }
 
-        index_entries.push(IndexEntry {
-            ctime: (md.ctime() as u32, md.ctime_nsec() as u32),
-            mtime: (md.mtime() as u32, md.mtime_nsec() as u32),
-            device: md.dev() as u32,
-            inode: md.ino() as u32,
-            mode: md.mode() as u32,
-            size: md.size() as u32,
-            sha_hash: hash_vec,
-            file_path_len,
-            file_path,
-        });
-        header.increment_num_entries();
-        success += 1;
+    // A modified file replaces its entry in place. The old blob
+    // stays in the objects, earlier commits may still refer to it.
+    let entry = IndexEntry::from_metadata(file_path.clone(), hash_vec.clone(), &md);
+    match existing_files.get_mut(&file_path) {
+        Some((idx, existing_hash)) => {
+            index_entries[*idx] = entry;
+            *existing_hash = hash_vec;
+        }
+        None => {
+            existing_files.insert(file_path, (index_entries.len(), hash_vec));
+            index_entries.push(entry);
+            header.increment_num_entries();
+        }
     }
 
-    if success > 0 {
-        write_index(header, index_entries)?;
+    Ok(AddOutcome::Added)
+}
+
+/// Reports the paths `add_paths` skipped, an error if there are any.
+pub fn print_add_outcomes(outcomes: &[(PathBuf, AddOutcome)]) -> RitResult<()> {
+    let mut success: usize = 0;
+    for (path, outcome) in outcomes.iter() {
+        match outcome {
+            AddOutcome::Added => success += 1,
+            AddOutcome::Unchanged => {
+                println!("this file already added.");
+                success += 1;
+            }
+            AddOutcome::Ignored => {
+                println!("{} is ignored by a .ritignore; skipping...", path.display())
+            }
+            AddOutcome::IsDir => println!("{} is a dir. Choose file please.", path.display()),
+            AddOutcome::NotFound => println!("{} does NOT exist; skipping...", path.display()),
+        }
     }
 
-    if success != all_paths_len {
-        Err("unsuccessful add operation".into())
+    if success != outcomes.len() {
+        Err(RitError::InvalidInput(
+            "unsuccessful add operation".to_string(),
+        ))
     } else {
-        Ok(true)
+        Ok(())
     }
 }
@@ -0,0 +1,99 @@
+use crate::models::errormodels::{RitError, RitResult};
+use crate::utils::{colorutils, ioutils, refutils};
+
+fn check_branch_name(name: &str) -> RitResult<()> {
+    if !refutils::is_valid_branch_name(name) {
+        return Err(RitError::InvalidInput(format!(
+            "'{}' is not a valid branch name",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Creates a new branch pointing at the commit HEAD points to.
+pub fn create_branch_rit(name: &str) -> RitResult<()> {
+    check_branch_name(name)?;
+
+    if refutils::read_branch(name)?.is_some() {
+        return Err(RitError::AlreadyExists(format!(
+            "a branch named '{}' already exists",
+            name
+        )));
+    }
+
+    let commit_hash = match refutils::resolve_head()? {
+        Some(h) => h,
+        None => {
+            return Err(RitError::NotFound(
+                "HEAD does not point to any commit yet".to_string(),
+            ))
+        }
+    };
+
+    Ok(refutils::write_branch(name, &commit_hash)?)
+}
+
+/// Lists all branches, the current one is marked with "*".
+pub fn list_branches_rit() -> RitResult<()> {
+    ioutils::get_objects_path()?;
+
+    let current = refutils::current_branch()?;
+    if current.is_none() {
+        if let Some(hash) = refutils::resolve_head()? {
+            let label = format!("(HEAD detached at {})", &hex::encode(hash)[..7]);
+            println!("* {}", colorutils::paint(&label, colorutils::GREEN));
+        }
+    }
+
+    for branch in refutils::list_branches()? {
+        if current.as_ref() == Some(&branch) {
+            println!("* {}", colorutils::paint(&branch, colorutils::GREEN));
+        } else {
+            println!("  {}", branch);
+        }
+    }
+
+    Ok(())
+}
+
+/// Deletes a branch. Unless `force` is set, the branch must be
+/// fully merged, i.e. its tip is reachable from HEAD.
+pub fn delete_branch_rit(name: &str, force: bool) -> RitResult<()> {
+    let objects_path = ioutils::get_objects_path()?;
+    check_branch_name(name)?;
+
+    if refutils::current_branch()?.as_deref() == Some(name) {
+        return Err(RitError::InvalidInput(format!(
+            "cannot delete the current branch '{}'",
+            name
+        )));
+    }
+
+    let branch_hash = match refutils::read_branch(name)? {
+        Some(h) => h,
+        None => return Err(RitError::NotFound(format!("branch '{}' not found", name))),
+    };
+
+    if !force {
+        let merged = match refutils::resolve_head()? {
+            Some(tip) => refutils::is_ancestor(&objects_path, &branch_hash, &tip)?,
+            None => false,
+        };
+        if !merged {
+            return Err(RitError::InvalidInput(format!(
+                "branch '{}' is not fully merged\nto delete it anyway:\n>> rit branch -D {}",
+                name, name
+            )));
+        }
+    }
+
+    refutils::delete_branch(name)?;
+    println!(
+        "Deleted branch {} (was {})",
+        name,
+        &hex::encode(&branch_hash)[..7]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::{self, ErrorKind},
+    os::unix::fs::PermissionsExt,
+    path::Path,
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    models::{
+        errormodels::{RitError, RitResult},
+        indexmodels::{IndexEntry, IndexHeader, INDEX_SIGNATURE, INDEX_VERSION},
+        refmodels::Head,
+        treemodels::TreeEntry,
+    },
+    ops::branch::create_branch_rit,
+    repository,
+    utils::{ioutils, refutils},
+};
+
+/// Writes the blob of a tree entry to the working tree and
+/// returns the index entry describing the written file.
+fn write_worktree_file(objects_path: &Path, te: &TreeEntry) -> io::Result<IndexEntry> {
+    let content = ioutils::read_blob(objects_path, &te.sha_hash)?;
+
+    let path = repository::work_tree_path(&te.file_path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(te.mode & 0o777))?;
+
+    let md = fs::metadata(&path)?;
+    Ok(IndexEntry::from_metadata(
+        te.file_path.clone(),
+        te.sha_hash.clone(),
+        &md,
+    ))
+}
+
+/// Local changes are only a problem for paths that differ between
+/// HEAD and the target, the others are carried over untouched.
+/// Returns the paths whose changes would be lost.
+fn find_conflicts(
+    head_tree: &HashMap<String, TreeEntry>,
+    target_tree: &HashMap<String, TreeEntry>,
+    index: &HashMap<String, IndexEntry>,
+) -> io::Result<Vec<String>> {
+    let all_paths: BTreeSet<&String> = head_tree
+        .keys()
+        .chain(target_tree.keys())
+        .chain(index.keys())
+        .collect();
+
+    let mut conflicts = vec![];
+    for path in all_paths {
+        let head_hash = head_tree.get(path).map(|te| &te.sha_hash);
+        let target_hash = target_tree.get(path).map(|te| &te.sha_hash);
+        if head_hash == target_hash {
+            continue;
+        }
+
+        let index_hash = index.get(path).map(|ie| &ie.sha_hash);
+        let worktree_hash = ioutils::get_worktree_hash(path)?;
+
+        // already in the target state, nothing can get lost
+        if index_hash == target_hash && worktree_hash.as_ref() == target_hash {
+            continue;
+        }
+
+        let is_dirty = match index_hash {
+            Some(_) => index_hash != head_hash || worktree_hash.as_ref() != index_hash,
+            // an untracked file would be overwritten by the target
+            None => head_hash.is_some() || worktree_hash.is_some(),
+        };
+        if is_dirty {
+            conflicts.push(path.clone());
+        }
+    }
+
+    Ok(conflicts)
+}
+
+/// Work tree paths that are in the way of a target file without
+/// being removed by the switch: a dir with more than the HEAD-only
+/// files in it where the target has a file, or an untracked
+/// file where the target needs a dir.
+fn find_blockers(
+    head_tree: &HashMap<String, TreeEntry>,
+    target_tree: &HashMap<String, TreeEntry>,
+) -> io::Result<BTreeSet<String>> {
+    let removed = |path: &str| head_tree.contains_key(path) && !target_tree.contains_key(path);
+    let is_dir = |path: &str| match fs::symlink_metadata(repository::work_tree_path(path)) {
+        Ok(md) => Ok(Some(md.is_dir())),
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) => Ok(None),
+        Err(e) => Err(e),
+    };
+
+    let mut blockers = BTreeSet::new();
+    for path in target_tree.keys() {
+        if is_dir(path)? == Some(true) {
+            let root = repository::work_tree_path(path);
+            for entry in WalkDir::new(&root).min_depth(1) {
+                let entry = entry?;
+                let rel = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+                let entry_path = format!("{}/{}", path, rel.to_string_lossy());
+                let in_the_way = match entry.file_type().is_dir() {
+                    // dirs go away with their last file, empty ones never do
+                    true => fs::read_dir(entry.path())?.next().is_none(),
+                    false => !removed(&entry_path),
+                };
+                if in_the_way {
+                    blockers.insert(path.clone());
+                    break;
+                }
+            }
+        }
+
+        for dir in Path::new(path).ancestors().skip(1) {
+            let dir = dir.to_string_lossy();
+            if dir.is_empty() || dir == "." {
+                break;
+            }
+            if is_dir(&dir)? == Some(false) && !removed(&dir) {
+                blockers.insert(dir.to_string());
+            }
+        }
+    }
+
+    Ok(blockers)
+}
+
+/// When `checkout_rit` detaches HEAD instead of checking out a branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetachMode {
+    /// the target has to be a branch
+    Never,
+    /// only for targets that are not a branch
+    NonBranch,
+    /// also for a branch, HEAD then points to its commit
+    Always,
+}
+
+/// Switches the working tree, INDEX and HEAD to a branch or commit.
+/// - `create_branch` creates `target` as a new branch at HEAD first
+/// - `force` discards local changes instead of refusing to switch
+/// - `detach` decides when HEAD is detached, see `DetachMode`
+pub fn checkout_rit(
+    target: &str,
+    create_branch: bool,
+    force: bool,
+    detach: DetachMode,
+) -> RitResult<()> {
+    let objects_path = ioutils::get_objects_path()?;
+
+    if !create_branch {
+        return switch_to(&objects_path, target, force, detach);
+    }
+
+    create_branch_rit(target)?;
+    let res = switch_to(&objects_path, target, force, detach);
+    // a failed switch takes the branch it was meant for with it
+    if res.is_err() {
+        refutils::delete_branch(target)?;
+    }
+    res
+}
+
+fn switch_to(objects_path: &Path, target: &str, force: bool, detach: DetachMode) -> RitResult<()> {
+    let (new_head, target_hash) = match refutils::read_branch(target)? {
+        Some(hash) if refutils::is_valid_branch_name(target) && detach != DetachMode::Always => {
+            (Head::Branch(target.to_string()), hash)
+        }
+        _ => {
+            let hash = refutils::resolve_revision(objects_path, target)?;
+            if detach == DetachMode::Never {
+                return Err(RitError::InvalidInput(format!(
+                    "'{}' is not a branch\nto check out the commit:\n>> rit switch --detach {}",
+                    target, target
+                )));
+            }
+            (Head::Detached(hash.clone()), hash)
+        }
+    };
+
+    if refutils::read_head()? == new_head {
+        if let Head::Branch(name) = &new_head {
+            println!("Already on '{}'", name);
+            return Ok(());
+        }
+    }
+
+    let head_hash = refutils::resolve_head()?;
+    let head_tree = ioutils::get_tree_map(objects_path, head_hash.as_ref())?;
+    let target_tree = ioutils::get_tree_map(objects_path, Some(&target_hash))?;
+
+    let lock = ioutils::lock_index()?;
+    let index: HashMap<String, IndexEntry> = match ioutils::read_index() {
+        Ok((_, ies)) => ies
+            .into_iter()
+            .map(|ie| (ie.file_path.clone(), ie))
+            .collect(),
+        Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let blockers = find_blockers(&head_tree, &target_tree)?;
+    if !force {
+        let mut conflicts = find_conflicts(&head_tree, &target_tree, &index)?;
+        conflicts.extend(blockers.iter().cloned());
+        if !conflicts.is_empty() {
+            return Err(RitError::MergeConflict(conflicts));
+        }
+    }
+
+    // fail before touching the working tree if content is missing
+    for te in target_tree.values() {
+        let hash = hex::encode(&te.sha_hash);
+        if !ioutils::object_exists(objects_path, &hash) {
+            return Err(RitError::MissingObject(hash));
+        }
+    }
+
+    let mut new_entries: Vec<IndexEntry> = vec![];
+    let mut index = index;
+
+    // files that only HEAD knows about are deleted
+    for (path, te) in head_tree.iter() {
+        if !target_tree.contains_key(path)
+            && (force || index.get(path).map(|ie| &ie.sha_hash) == Some(&te.sha_hash))
+        {
+            ioutils::remove_worktree_file(path)?;
+            index.remove(path);
+        }
+    }
+
+    // with --force untracked files in the way are discarded too
+    for path in blockers.iter() {
+        let blocker = repository::work_tree_path(path);
+        match fs::symlink_metadata(&blocker).is_ok_and(|md| md.is_dir()) {
+            true => fs::remove_dir_all(&blocker)?,
+            false => ioutils::remove_worktree_file(path)?,
+        }
+    }
+
+    for (path, te) in target_tree.iter() {
+        let unchanged = head_tree.get(path).map(|h| &h.sha_hash) == Some(&te.sha_hash);
+        match index.remove(path) {
+            // carry over local changes of files the switch does not touch
+            Some(ie) if unchanged && !force => new_entries.push(ie),
+            // a staged deletion is carried over as well
+            None if unchanged && !force => {}
+            _ => new_entries.push(write_worktree_file(objects_path, te)?),
+        }
+    }
+
+    // staged files unknown to both HEAD and the target stay staged
+    if !force {
+        new_entries.extend(
+            index
+                .into_values()
+                .filter(|ie| !head_tree.contains_key(&ie.file_path)),
+        );
+    }
+
+    new_entries.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    let header = IndexHeader::new(new_entries.len() as u32, INDEX_VERSION, INDEX_SIGNATURE);
+    ioutils::write_index_locked(lock, header, new_entries)?;
+
+    refutils::write_head(&new_head)?;
+
+    match &new_head {
+        Head::Branch(name) => println!("Switched to branch '{}'", name),
+        Head::Detached(hash) => {
+            let commit = ioutils::read_commit(objects_path, hash)?;
+            println!(
+                "HEAD is now at {} {}",
+                &hex::encode(hash)[..7],
+                commit.message.lines().next().unwrap_or("")
+            );
+        }
+    }
+
+    Ok(())
+}
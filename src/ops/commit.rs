@@ -1,54 +1,221 @@
 use crate::{
-    models::indexmodels::IndexEntry,
-    utils::{hashutils::get_hash_from_file, ioutils},
+    models::{
+        commitmodels::Signature,
+        errormodels::{RitError, RitResult},
+        indexmodels::IndexEntry,
+        objectmodels::ObjectKind,
+        treemodels::{serialize_tree, TreeEntry, TREE_MODE},
+    },
+    utils::{configutils, ioutils, lockutils::LockFile, refutils, timeutils},
 };
 use std::{
-    fs, io,
-    io::Write,
-    path::{Path, PathBuf},
+    collections::{btree_map::Entry, BTreeMap},
+    env,
+    io::{self, Error, ErrorKind},
+    path::Path,
 };
 
+const DEFAULT_TIMEZONE: &str = "+0000";
+
+/// "Name <email>" as passed to `--author`.
+fn parse_name_email(ident: &str) -> Option<(String, String)> {
+    let (name, rest) = ident.split_once('<')?;
+    let (email, _) = rest.split_once('>')?;
+    Some((name.trim().to_string(), email.trim().to_string()))
+}
+
+fn env_value(var: &str) -> Option<String> {
+    env::var(var).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Builds the author or committer signature. Precedence, highest first:
+/// - `--author` / `--date` (author only)
+/// - RIT_AUTHOR_NAME, RIT_AUTHOR_EMAIL, RIT_AUTHOR_DATE (or RIT_COMMITTER_*)
+/// - user.name, user.email and user.timezone from the local,
+///   global and system config, in that order
+/// - the current time
+fn resolve_signature(
+    role: &str,
+    ident_override: Option<&str>,
+    date_override: Option<&str>,
+) -> io::Result<Signature> {
+    let env_prefix = format!("RIT_{}", role.to_uppercase());
+
+    let (mut name, mut email) = (None, None);
+    if let Some(ident) = ident_override {
+        let (n, e) = parse_name_email(ident).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("--author '{}' is not in the form 'Name <email>'", ident),
+            )
+        })?;
+        (name, email) = (Some(n), Some(e));
+    }
+
+    let name = match name.or_else(|| env_value(&format!("{}_NAME", env_prefix))) {
+        Some(n) => Some(n),
+        None => configutils::get_config_value("user.name")?,
+    };
+    let email = match email.or_else(|| env_value(&format!("{}_EMAIL", env_prefix))) {
+        Some(e) => Some(e),
+        None => configutils::get_config_value("user.email")?,
+    };
+
+    let (name, email) = match (name, email) {
+        (Some(n), Some(e)) => (n, e),
+        _ => {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "{} identity unknown\n\nset it with:\n>> rit config --global user.name \"Your Name\"\n\
+                     >> rit config --global user.email you@example.com\n\n\
+                     or with {}_NAME and {}_EMAIL",
+                    role, env_prefix, env_prefix
+                ),
+            ))
+        }
+    };
+
+    // anything that would break up the author or committer line
+    for (field, value) in [("name", &name), ("email", &email)] {
+        if value
+            .chars()
+            .any(|c| c.is_control() || c == '<' || c == '>')
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} {} {:?} must not contain '<', '>' or control characters",
+                    role, field, value
+                ),
+            ));
+        }
+    }
+
+    let default_tz = configutils::get_config_value("user.timezone")?
+        .and_then(|tz| timeutils::normalize_timezone(&tz))
+        .unwrap_or_else(|| DEFAULT_TIMEZONE.to_string());
+
+    let date = date_override
+        .map(|d| d.to_string())
+        .or_else(|| env_value(&format!("{}_DATE", env_prefix)));
+    let (timestamp, timezone) = match date {
+        Some(d) => timeutils::parse_date(&d, &default_tz).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid date format: {}", d),
+            )
+        })?,
+        None => (timeutils::now(), default_tz),
+    };
+
+    Ok(Signature {
+        name,
+        email,
+        timestamp: Some(timestamp),
+        timezone: Some(timezone),
+    })
+}
+
+/// Index entries grouped by directory, children sorted by name.
+enum TreeNode<'a> {
+    File(&'a IndexEntry),
+    Dir(BTreeMap<&'a str, TreeNode<'a>>),
+}
+
+/// A path that is a file in one INDEX entry and a dir in another
+/// cannot be written as a tree, so it is an error.
+fn build_tree_nodes(ies: &[IndexEntry]) -> io::Result<BTreeMap<&str, TreeNode<'_>>> {
+    let collision = |path: &str| {
+        Error::from(RitError::InvalidInput(format!(
+            "'{}' is both a file and a directory in INDEX\n\
+             remove one of them with:\n>> rit rm --cached <path>",
+            path
+        )))
+    };
+
+    let mut root: BTreeMap<&str, TreeNode> = BTreeMap::new();
+
+    for ie in ies.iter() {
+        let rel_path = ie.file_path.strip_prefix("./").unwrap_or(&ie.file_path);
+        let mut components: Vec<&str> = rel_path.split('/').filter(|c| !c.is_empty()).collect();
+        let file_name = match components.pop() {
+            Some(f) => f,
+            None => continue,
+        };
+
+        let mut dir = &mut root;
+        for comp in components {
+            let node = dir
+                .entry(comp)
+                .or_insert_with(|| TreeNode::Dir(BTreeMap::new()));
+            dir = match node {
+                TreeNode::Dir(children) => children,
+                TreeNode::File(file) => return Err(collision(&file.file_path)),
+            };
+        }
+        match dir.entry(file_name) {
+            Entry::Vacant(e) => {
+                e.insert(TreeNode::File(ie));
+            }
+            Entry::Occupied(e) => match e.get() {
+                TreeNode::Dir(_) => return Err(collision(&ie.file_path)),
+                // duplicate INDEX entries, the last one wins
+                TreeNode::File(_) => {
+                    *e.into_mut() = TreeNode::File(ie);
+                }
+            },
+        }
+    }
+
+    Ok(root)
+}
+
 /// Tree file which contains:
 /// - <MODE> <FILENAME> <HASH>
 /// - where <MODE> is permission in octal. (100644 normal files) (100755 executables) (040000 subdirs), etc.
-fn write_tree_file(objects_path: &PathBuf, ies: Vec<IndexEntry>) -> io::Result<Vec<u8>> {
-    let mut tree_content: Vec<u8> = Vec::new();
+///
+/// Every dir gets its own tree, so unchanged subdirs
+/// keep their hash and are shared between commits.
+fn write_tree_nodes(objects_path: &Path, nodes: &BTreeMap<&str, TreeNode>) -> io::Result<Vec<u8>> {
+    let mut entries: Vec<TreeEntry> = Vec::new();
 
-    for ie in ies.iter() {
-        // 4 bytes for mode
-        tree_content.extend_from_slice(&ie.mode.to_be_bytes());
-        // 4 bytes for file_path_len
-        tree_content.extend_from_slice(&ie.file_path_len.to_be_bytes());
-        // unknows bytes for file_path (inferred from the previous 4 bytes)
-        tree_content.extend_from_slice(ie.file_path.as_bytes());
-        // 32 bytes for sha hash
-        tree_content.extend_from_slice(&ie.sha_hash[..]);
-
-        // Adding extra 0 if necessary for 8-byte alignment
-        let offset = tree_content.len() % 8;
-        if offset > 0 {
-            let padding = 8 - offset;
-            tree_content.extend_from_slice(&vec![0; padding]);
-        }
+    for (name, node) in nodes.iter() {
+        let (mode, sha_hash) = match node {
+            TreeNode::File(ie) => (ie.mode, ie.sha_hash.clone()),
+            TreeNode::Dir(children) => (TREE_MODE, write_tree_nodes(objects_path, children)?),
+        };
+        entries.push(TreeEntry {
+            mode,
+            file_path: name.to_string(),
+            sha_hash,
+        });
     }
 
-    let (tree_file_name, tree_file_hash) = get_hash_from_file(&tree_content);
-    ioutils::save_file_hash(&tree_file_name, objects_path, &tree_content)?;
+    let tree_content = serialize_tree(&entries);
+    let (_, tree_file_hash) = ioutils::write_object(objects_path, ObjectKind::Tree, &tree_content)?;
 
     Ok(tree_file_hash)
 }
 
+/// Writes the trees of the INDEX and returns the hash of the root tree.
+fn write_tree_file(objects_path: &Path, ies: Vec<IndexEntry>) -> io::Result<Vec<u8>> {
+    write_tree_nodes(objects_path, &build_tree_nodes(&ies)?)
+}
+
 /// Commit file consists of:
 /// - <TREE_HASH> (32 bytes)
 /// - parent existence flag (1 byte)
 /// - <COMMIT_HASH> (32 bytes)
-/// - <AUTHOR_NAME> <EMAIL> <DATE_SEC> <PERMISSION> (variable bytes)
-/// - com <COMMITTER_NAME> <EMAIL> <DATE_SEC> <PERMISSION> (variable bytes)
+/// - <AUTHOR_NAME> <EMAIL> <DATE_SEC> <TIMEZONE> (variable bytes)
+/// - <COMMITTER_NAME> <EMAIL> <DATE_SEC> <TIMEZONE> (variable bytes)
 ///
 /// <COMMIT_MSG> (variable bytes)
 fn prepare_commit_content(
-    parent_commit_hash: &Vec<u8>,
+    parent_commit_hash: &[u8],
     tree_file_hash: Vec<u8>,
+    author: &Signature,
+    committer: &Signature,
     commit_msg: &str,
 ) -> Vec<u8> {
     let mut commit_content: Vec<u8> = Vec::new();
@@ -61,17 +228,17 @@ fn prepare_commit_content(
     // 32 bytes hash sha256
     if !parent_commit_hash.is_empty() {
         commit_content.push(0b00000001);
-        commit_content.extend_from_slice(&parent_commit_hash[..]);
+        commit_content.extend_from_slice(parent_commit_hash);
     } else {
         commit_content.push(0b00000000);
-        commit_content.extend_from_slice(&vec![0; 32]);
+        commit_content.extend_from_slice(&[0; 32]);
     }
 
     // unknown number of bytes, read until 10
-    commit_content.extend_from_slice(b"saeid saeidalz96@gmail.com\n");
+    commit_content.extend_from_slice(format!("{}\n", author).as_bytes());
 
     // unknown number of bytes, read until 10
-    commit_content.extend_from_slice(b"saeid saeidalz96@gmail.com\n");
+    commit_content.extend_from_slice(format!("{}\n", committer).as_bytes());
 
     // one byte for new line
     commit_content.push(b'\n');
@@ -81,86 +248,56 @@ fn prepare_commit_content(
     commit_content
 }
 
-fn write_commit_file(objects_path: &PathBuf, commit_content: Vec<u8>) -> io::Result<Vec<u8>> {
-    let (commit_file_name, commit_file_hash) = get_hash_from_file(&commit_content);
-    ioutils::save_file_hash(&commit_file_name, &objects_path, &commit_content)?;
+fn write_commit_file(objects_path: &Path, commit_content: Vec<u8>) -> io::Result<Vec<u8>> {
+    let (_, commit_file_hash) =
+        ioutils::write_object(objects_path, ObjectKind::Commit, &commit_content)?;
 
     Ok(commit_file_hash)
 }
 
-fn fetch_parent_commit_hash(main_file: &Path) -> io::Result<Vec<u8>> {
-    let parent_commit_hash = match main_file.exists() {
-        true => fs::read(main_file)?,
-        false => Vec::new(),
-    };
-
-    Ok(parent_commit_hash)
-}
-
-fn write_head_commit(
-    parent_commit_exists: bool,
-    main_file: &Path,
-    commit_file_name: Vec<u8>,
-) -> io::Result<()> {
-    let mut f: fs::File;
-    if parent_commit_exists {
-        f = fs::OpenOptions::new().write(true).open(main_file)?;
-    } else {
-        fs::create_dir_all(main_file.parent().unwrap())?;
-        f = fs::File::create(main_file).unwrap();
-    }
-    f.write_all(&commit_file_name)?;
-    Ok(())
+/// Advances the checked out branch, or moves HEAD itself when
+/// detached, through the lock taken with `lock_head_ref`.
+fn write_head_commit(mut ref_lock: LockFile, commit_file_name: &[u8]) -> io::Result<()> {
+    ref_lock.write_all(commit_file_name)?;
+    ref_lock.commit()
 }
 
-pub fn commit_rit(commit_msg: &str) {
-    let objects_path = match ioutils::get_objects_path() {
-        Ok(op) => op,
-        Err(e) => {
-            eprintln!("Error Getting 'objects' Path: {}", e);
-            return;
-        }
-    };
+/// Records the INDEX as a new commit on top of HEAD and returns its hash,
+/// `None` when the INDEX matches the tree of HEAD.
+/// `author` ("Name <email>") and `date` override the configured
+/// author identity and the current time for the author.
+pub fn create_commit(
+    commit_msg: &str,
+    author: Option<&str>,
+    date: Option<&str>,
+) -> RitResult<Option<Vec<u8>>> {
+    let objects_path = ioutils::get_objects_path()?;
 
-    let main_file = Path::new("./.rit/refs/heads/main");
-    let parent_commit_hash = match fetch_parent_commit_hash(main_file) {
-        Ok(pa) => pa,
-        Err(e) => {
-            eprintln!("Error Fetching Parent Commit Hash: {}", e);
-            return;
-        }
-    };
+    let author = resolve_signature("author", author, date)?;
+    let committer = resolve_signature("committer", None, None)?;
 
-    let index_entries = match ioutils::read_index() {
-        Ok((_, ie)) => ie,
-        Err(e) => {
-            eprintln!("Error Reading Index: {}", e);
-            return;
-        }
-    };
+    let (ref_lock, parent_commit_hash) = refutils::lock_head_ref()?;
+    let parent_commit_hash = parent_commit_hash.unwrap_or_default();
+    let (_, index_entries) = ioutils::read_index()?;
+    let tree_file_hash = write_tree_file(&objects_path, index_entries)?;
 
-    let tree_file_hash = match write_tree_file(&objects_path, index_entries) {
-        Ok(p) => p,
-        Err(e) => {
-            match e.kind() {
-                io::ErrorKind::AlreadyExists => println!("** Everything Up-to-date **"),
-                _ => eprintln!("Error Writing Tree File: {}", e),
-            }
-            return;
+    if !parent_commit_hash.is_empty() {
+        let parent = ioutils::read_commit(&objects_path, &parent_commit_hash)?;
+        if parent.tree_hash == tree_file_hash {
+            return Ok(None);
         }
-    };
+    }
 
-    let commit_content = prepare_commit_content(&parent_commit_hash, tree_file_hash, commit_msg);
+    let commit_content = prepare_commit_content(
+        &parent_commit_hash,
+        tree_file_hash,
+        &author,
+        &committer,
+        commit_msg,
+    );
 
-    let commit_file_hash = match write_commit_file(&objects_path, commit_content) {
-        Ok(res) => res,
-        Err(e) => {
-            eprintln!("{}", e);
-            return;
-        }
-    };
+    let commit_file_hash = write_commit_file(&objects_path, commit_content)?;
+    write_head_commit(ref_lock, &commit_file_hash)?;
 
-    if let Err(e) = write_head_commit(!parent_commit_hash.is_empty(), main_file, commit_file_hash) {
-        eprintln!("Error Writing Head Commit: {}", e);
-    }
+    Ok(Some(commit_file_hash))
 }
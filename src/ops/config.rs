@@ -0,0 +1,70 @@
+use std::io::{self, Error, ErrorKind};
+
+use crate::models::{configmodels::ConfigLevel, errormodels::RitResult};
+use crate::utils::{configutils, ioutils};
+
+/// The local config only exists inside a repository.
+fn check_level(level: ConfigLevel) -> io::Result<()> {
+    if level == ConfigLevel::Local {
+        ioutils::get_objects_path()?;
+    }
+    Ok(())
+}
+
+/// Prints the value of a key. Without a level, the value
+/// of the most specific level defining it is shown.
+pub fn config_get_rit(key: &str, level: Option<ConfigLevel>) -> RitResult<()> {
+    let value = match level {
+        Some(l) => {
+            let key = key.to_lowercase();
+            configutils::read_config_level(l)?
+                .into_iter()
+                .rfind(|(k, _)| k.to_lowercase() == key)
+                .map(|(_, v)| v)
+        }
+        None => configutils::get_config_value(key)?,
+    };
+
+    match value {
+        Some(v) => {
+            println!("{}", v);
+            Ok(())
+        }
+        None => Err(Error::new(ErrorKind::NotFound, format!("key '{}' is not set", key)).into()),
+    }
+}
+
+/// Writes to the local config unless another level is given.
+pub fn config_set_rit(key: &str, value: &str, level: Option<ConfigLevel>) -> RitResult<()> {
+    let level = level.unwrap_or(ConfigLevel::Local);
+    check_level(level)?;
+    Ok(configutils::set_config_value(level, key, value)?)
+}
+
+pub fn config_unset_rit(key: &str, level: Option<ConfigLevel>) -> RitResult<()> {
+    let level = level.unwrap_or(ConfigLevel::Local);
+    check_level(level)?;
+
+    match configutils::unset_config_value(level, key)? {
+        true => Ok(()),
+        false => Err(Error::new(
+            ErrorKind::NotFound,
+            format!("key '{}' is not set in the {} config", key, level),
+        )
+        .into()),
+    }
+}
+
+/// Lists "key=value" for every entry, in precedence order.
+pub fn config_list_rit(level: Option<ConfigLevel>, show_origin: bool) -> RitResult<()> {
+    for (l, key, value) in configutils::list_config()? {
+        if level.is_some_and(|wanted| wanted != l) {
+            continue;
+        }
+        match show_origin {
+            true => println!("{}\t{}={}", l, key, value),
+            false => println!("{}={}", key, value),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,255 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, ErrorKind},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    models::{
+        errormodels::{RitError, RitResult},
+        objectmodels::ObjectKind,
+    },
+    repository,
+    utils::{
+        colorutils,
+        diffutils::{build_hunks, is_binary, myers_diff, split_lines, DiffOp},
+        hashutils::hash_object,
+        ioutils, pathutils, refutils,
+    },
+};
+
+/// One side of a diff: path -> (mode, blob hash). Content is read
+/// from the working tree or from the objects, depending on the side.
+struct Snapshot {
+    entries: BTreeMap<String, (u32, Vec<u8>)>,
+    from_worktree: bool,
+}
+
+impl Snapshot {
+    fn read_content(&self, objects_path: &Path, path: &str) -> io::Result<Vec<u8>> {
+        match self.from_worktree {
+            true => fs::read(repository::work_tree_path(path)),
+            false => ioutils::read_blob(objects_path, &self.entries[path].1),
+        }
+    }
+}
+
+fn index_snapshot() -> io::Result<Snapshot> {
+    let entries = match ioutils::read_index() {
+        Ok((_, ies)) => ies
+            .into_iter()
+            .map(|ie| (ie.file_path, (ie.mode, ie.sha_hash)))
+            .collect(),
+        Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+        Err(e) => return Err(e),
+    };
+
+    Ok(Snapshot {
+        entries,
+        from_worktree: false,
+    })
+}
+
+fn commit_snapshot(objects_path: &Path, commit_hash: Option<&Vec<u8>>) -> io::Result<Snapshot> {
+    let mut entries = BTreeMap::new();
+    if let Some(hash) = commit_hash {
+        let commit = ioutils::read_commit(objects_path, hash)?;
+        for te in ioutils::read_tree_recursive(objects_path, &commit.tree_hash)? {
+            entries.insert(te.file_path, (te.mode, te.sha_hash));
+        }
+    }
+
+    Ok(Snapshot {
+        entries,
+        from_worktree: false,
+    })
+}
+
+/// The working tree state of the given paths, deleted files are left out.
+fn worktree_snapshot<'a>(paths: impl Iterator<Item = &'a String>) -> io::Result<Snapshot> {
+    let mut entries = BTreeMap::new();
+    for path in paths {
+        let worktree_path = repository::work_tree_path(path);
+        let content = match fs::read(&worktree_path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let mode = fs::metadata(&worktree_path)?.mode();
+        let (_, hash) = hash_object(ObjectKind::Blob, &content);
+        entries.insert(path.clone(), (mode, hash));
+    }
+
+    Ok(Snapshot {
+        entries,
+        from_worktree: true,
+    })
+}
+
+fn short_hash(hash: Option<&Vec<u8>>) -> String {
+    match hash {
+        Some(h) => hex::encode(h)[..7].to_string(),
+        None => "0000000".to_string(),
+    }
+}
+
+fn print_line(prefix: &str, line: &[u8], color: Option<&str>) {
+    let text = String::from_utf8_lossy(line);
+    let stripped = text.strip_suffix('\n');
+    let out = format!("{}{}", prefix, stripped.unwrap_or(&text));
+    match color {
+        Some(c) => println!("{}", colorutils::paint(&out, c)),
+        None => println!("{}", out),
+    }
+    if stripped.is_none() {
+        println!("\\ No newline at end of file");
+    }
+}
+
+fn print_file_diff(
+    objects_path: &Path,
+    path: &str,
+    old: &Snapshot,
+    new: &Snapshot,
+    context: usize,
+) -> io::Result<()> {
+    let old_entry = old.entries.get(path);
+    let new_entry = new.entries.get(path);
+    let name = path.strip_prefix("./").unwrap_or(path);
+
+    println!("diff --rit a/{} b/{}", name, name);
+    match (old_entry, new_entry) {
+        (None, Some((mode, _))) => println!("new file mode {:o}", mode),
+        (Some((mode, _)), None) => println!("deleted file mode {:o}", mode),
+        (Some((old_mode, _)), Some((new_mode, _))) if old_mode != new_mode => {
+            println!("old mode {:o}", old_mode);
+            println!("new mode {:o}", new_mode);
+        }
+        _ => {}
+    }
+
+    let old_hash = old_entry.map(|e| &e.1);
+    let new_hash = new_entry.map(|e| &e.1);
+    if old_hash == new_hash {
+        return Ok(());
+    }
+    println!("index {}..{}", short_hash(old_hash), short_hash(new_hash));
+
+    let old_content = match old_entry {
+        Some(_) => old.read_content(objects_path, path)?,
+        None => vec![],
+    };
+    let new_content = match new_entry {
+        Some(_) => new.read_content(objects_path, path)?,
+        None => vec![],
+    };
+
+    let old_label = old_entry.map_or("/dev/null".to_string(), |_| format!("a/{}", name));
+    let new_label = new_entry.map_or("/dev/null".to_string(), |_| format!("b/{}", name));
+
+    if is_binary(&old_content) || is_binary(&new_content) {
+        println!("Binary files {} and {} differ", old_label, new_label);
+        return Ok(());
+    }
+
+    println!("--- {}", old_label);
+    println!("+++ {}", new_label);
+
+    let old_lines = split_lines(&old_content);
+    let new_lines = split_lines(&new_content);
+    let ops = myers_diff(&old_lines, &new_lines);
+
+    for hunk in build_hunks(&ops, context) {
+        // an empty range starts at the line before it
+        let old_start = hunk.old_start + usize::from(hunk.old_len > 0);
+        let new_start = hunk.new_start + usize::from(hunk.new_len > 0);
+        let header = format!(
+            "@@ -{},{} +{},{} @@",
+            old_start, hunk.old_len, new_start, hunk.new_len
+        );
+        println!("{}", colorutils::paint(&header, colorutils::YELLOW));
+
+        for op in hunk.ops {
+            match op {
+                DiffOp::Equal(i, _) => print_line(" ", old_lines[i], None),
+                DiffOp::Delete(i) => print_line("-", old_lines[i], Some(colorutils::RED)),
+                DiffOp::Insert(j) => print_line("+", new_lines[j], Some(colorutils::GREEN)),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Shows unified diffs between:
+/// - no revision: the INDEX and the working tree
+/// - `staged`: HEAD (or the given revision) and the INDEX
+/// - one revision: that commit and the working tree
+/// - two revisions: the two commits
+///
+/// `paths` limits the diff to these files or dirs and `context`
+/// is the number of unchanged lines shown around changes.
+pub fn diff_rit(
+    staged: bool,
+    revisions: Vec<String>,
+    paths: Vec<PathBuf>,
+    context: usize,
+) -> RitResult<()> {
+    let objects_path = ioutils::get_objects_path()?;
+
+    let mut commits = vec![];
+    for rev in revisions.iter() {
+        commits.push(refutils::resolve_revision(&objects_path, rev)?);
+    }
+
+    let (old, new) = match (staged, commits.as_slice()) {
+        (false, []) => {
+            let index = index_snapshot()?;
+            let worktree = worktree_snapshot(index.entries.keys())?;
+            (index, worktree)
+        }
+        (true, []) => {
+            let head = refutils::resolve_head()?;
+            (
+                commit_snapshot(&objects_path, head.as_ref())?,
+                index_snapshot()?,
+            )
+        }
+        (true, [commit]) => (
+            commit_snapshot(&objects_path, Some(commit))?,
+            index_snapshot()?,
+        ),
+        (false, [commit]) => {
+            let old = commit_snapshot(&objects_path, Some(commit))?;
+            let index = index_snapshot()?;
+            let tracked: BTreeSet<&String> =
+                old.entries.keys().chain(index.entries.keys()).collect();
+            let worktree = worktree_snapshot(tracked.into_iter())?;
+            (old, worktree)
+        }
+        (false, [from, to]) => (
+            commit_snapshot(&objects_path, Some(from))?,
+            commit_snapshot(&objects_path, Some(to))?,
+        ),
+        _ => {
+            return Err(RitError::InvalidInput(
+                "--staged takes at most one revision".to_string(),
+            ))
+        }
+    };
+
+    let filters: Vec<String> = paths.iter().map(|p| pathutils::to_rit_path(p)).collect();
+    let all_paths: BTreeSet<&String> = old.entries.keys().chain(new.entries.keys()).collect();
+    for path in all_paths {
+        if !filters.is_empty() && !filters.iter().any(|f| pathutils::is_under(path, f)) {
+            continue;
+        }
+        if old.entries.get(path) != new.entries.get(path) {
+            print_file_diff(&objects_path, path, &old, &new, context)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,241 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use crate::{
+    models::{
+        errormodels::{RitError, RitResult},
+        objectmodels::{Object, ObjectKind},
+    },
+    utils::{hashutils::get_hash_from_file, ioutils, packutils, refutils},
+};
+
+/// Kind of a stored object and the (hash, expected kind) of every
+/// object it refers to: the tree and parent of a commit, the
+/// entries of a tree.
+type ObjectRefs = (ObjectKind, Vec<(String, ObjectKind)>);
+
+fn object_refs(object: &Object) -> ObjectRefs {
+    let refs = match object {
+        Object::Blob(_) => vec![],
+        Object::Tree(entries) => entries
+            .iter()
+            .map(|te| {
+                let kind = match te.is_tree() {
+                    true => ObjectKind::Tree,
+                    false => ObjectKind::Blob,
+                };
+                (hex::encode(&te.sha_hash), kind)
+            })
+            .collect(),
+        Object::Commit(commit) => {
+            let mut refs = vec![(hex::encode(&commit.tree_hash), ObjectKind::Tree)];
+            if let Some(parent) = &commit.parent_hash {
+                refs.push((hex::encode(parent), ObjectKind::Commit));
+            }
+            refs
+        }
+    };
+
+    (object.kind(), refs)
+}
+
+/// Reads every loose and packed object, checks it hashes to its name
+/// and parses. Returns the refs of all readable objects.
+fn check_objects(
+    objects_path: &Path,
+    problems: &mut Vec<String>,
+) -> io::Result<BTreeMap<String, ObjectRefs>> {
+    for idx_path in packutils::list_pack_indexes(objects_path)? {
+        if let Err(e) = packutils::verify_pack(&idx_path) {
+            problems.push(format!("corrupt pack: {}", e));
+        }
+    }
+
+    let mut file_hashes: Vec<String> = ioutils::list_loose_objects(objects_path)?
+        .into_iter()
+        .map(|(h, _)| h)
+        .collect();
+    file_hashes.extend(packutils::list_packed_objects(objects_path)?);
+    file_hashes.sort();
+    file_hashes.dedup();
+
+    let mut objects = BTreeMap::new();
+    for file_hash in file_hashes {
+        let raw = match ioutils::read_raw_object(objects_path, &file_hash) {
+            Ok(r) => r,
+            Err(e) => {
+                problems.push(format!("unreadable object {}: {}", file_hash, e));
+                continue;
+            }
+        };
+
+        let (actual_hash, _) = get_hash_from_file(&raw);
+        if actual_hash != file_hash {
+            problems.push(format!(
+                "hash mismatch: object {} hashes to {}",
+                file_hash, actual_hash
+            ));
+            continue;
+        }
+
+        match Object::from_bytes(&raw) {
+            Ok(object) => {
+                objects.insert(file_hash, object_refs(&object));
+            }
+            Err(e) => problems.push(format!("invalid object {}: {}", file_hash, e)),
+        }
+    }
+
+    Ok(objects)
+}
+
+/// Walks everything reachable from `roots`, reporting referenced objects
+/// that are missing or of the wrong kind. Returns the reachable hashes.
+fn check_connectivity(
+    objects: &BTreeMap<String, ObjectRefs>,
+    roots: Vec<(String, String, ObjectKind)>,
+    problems: &mut Vec<String>,
+) -> HashSet<String> {
+    let mut reachable: HashSet<String> = HashSet::new();
+    // (hash, referrer, expected kind)
+    let mut pending = roots;
+
+    while let Some((file_hash, referrer, expected)) = pending.pop() {
+        if !reachable.insert(file_hash.clone()) {
+            continue;
+        }
+
+        let (kind, refs) = match objects.get(&file_hash) {
+            Some(o) => o,
+            None => {
+                problems.push(format!(
+                    "missing {} {} (referenced by {})",
+                    expected, file_hash, referrer
+                ));
+                continue;
+            }
+        };
+        if *kind != expected {
+            problems.push(format!(
+                "{} is a {}, not a {} (referenced by {})",
+                file_hash, kind, expected, referrer
+            ));
+            continue;
+        }
+
+        for (ref_hash, ref_kind) in refs.iter() {
+            pending.push((
+                ref_hash.clone(),
+                format!("{} {}", kind, file_hash),
+                *ref_kind,
+            ));
+        }
+    }
+
+    reachable
+}
+
+/// Checks the INDEX entries are well formed, unique and refer
+/// to existing blobs. Returns the roots the entries add.
+fn check_index(problems: &mut Vec<String>) -> io::Result<Vec<(String, String, ObjectKind)>> {
+    let ies = match ioutils::read_index() {
+        Ok((_, ies)) => ies,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => {
+            problems.push(e.to_string());
+            return Ok(vec![]);
+        }
+    };
+
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut roots = vec![];
+    for ie in ies.iter() {
+        let valid_path = ie.file_path.starts_with("./")
+            && ie.file_path.len() as u32 == ie.file_path_len
+            && ie.file_path[2..]
+                .split('/')
+                .all(|c| !c.is_empty() && c != "." && c != "..");
+        if !valid_path {
+            problems.push(format!("INDEX entry with invalid path '{}'", ie.file_path));
+        }
+        if !seen.insert(&ie.file_path) {
+            problems.push(format!("duplicate INDEX entry for {}", ie.file_path));
+        }
+        if ie.sha_hash.len() != 32 {
+            problems.push(format!("INDEX entry {} has an invalid hash", ie.file_path));
+            continue;
+        }
+
+        roots.push((
+            hex::encode(&ie.sha_hash),
+            format!("INDEX entry {}", ie.file_path),
+            ObjectKind::Blob,
+        ));
+    }
+
+    Ok(roots)
+}
+
+/// Verifies the integrity of the repository:
+/// 1. every object hashes to its name and parses, packs match their checksums
+/// 2. the branches and HEAD point to commits
+/// 3. everything reachable from them exists and has the expected kind
+/// 4. INDEX entries are well formed and their blobs exist
+///
+/// Unreachable objects nothing else refers to are reported as dangling.
+/// Fails when any problem was found.
+pub fn fsck_rit() -> RitResult<()> {
+    let objects_path = ioutils::get_objects_path()?;
+    let mut problems: Vec<String> = vec![];
+
+    let objects = check_objects(&objects_path, &mut problems)?;
+
+    let mut roots = vec![];
+    match refutils::list_ref_hashes() {
+        Ok(refs) => {
+            for (name, hash) in refs {
+                if hash.len() != 32 {
+                    problems.push(format!("{}: invalid ref", name));
+                    continue;
+                }
+                roots.push((hex::encode(hash), name, ObjectKind::Commit));
+            }
+        }
+        Err(e) => problems.push(format!("invalid refs: {}", e)),
+    }
+    roots.extend(check_index(&mut problems)?);
+
+    let reachable = check_connectivity(&objects, roots, &mut problems);
+
+    // unreachable objects referred to by other unreachable
+    // ones are covered by reporting the latter
+    let mut referenced: HashSet<&String> = HashSet::new();
+    for (file_hash, (_, refs)) in objects.iter() {
+        if !reachable.contains(file_hash) {
+            referenced.extend(refs.iter().map(|(h, _)| h));
+        }
+    }
+
+    for problem in problems.iter() {
+        println!("{}", problem);
+    }
+    for (file_hash, (kind, _)) in objects.iter() {
+        if !reachable.contains(file_hash) && !referenced.contains(file_hash) {
+            println!("dangling {} {}", kind, file_hash);
+        }
+    }
+
+    match problems.len() {
+        0 => {
+            println!("** No problems found **");
+            Ok(())
+        }
+        n => Err(RitError::CorruptRepository(format!(
+            "fsck found {} problems",
+            n
+        ))),
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Error, ErrorKind},
+    os::unix::fs::MetadataExt,
+    path::Path,
+};
+
+use crate::models::errormodels::RitResult;
+use crate::utils::{configutils, ioutils, packutils, refutils, timeutils};
+
+/// Unreachable objects younger than this are kept, they may belong
+/// to an `add` or `commit` that has not updated INDEX or a ref yet.
+const DEFAULT_PRUNE_EXPIRE: &str = "2.weeks.ago";
+
+/// Hex hashes of every object reachable from the branches and HEAD:
+/// the commits, their parents, trees and blobs, plus the blobs staged
+/// in INDEX. rit keeps no reflogs, so there are no other roots.
+/// Fails on a missing or corrupt commit or tree, as nothing can
+/// be safely deleted without knowing what it refers to.
+fn reachable_objects(objects_path: &Path) -> io::Result<HashSet<String>> {
+    let mut reachable: HashSet<String> = HashSet::new();
+    let mut commits: Vec<Vec<u8>> = refutils::list_ref_hashes()?
+        .into_iter()
+        .map(|(_, hash)| hash)
+        .collect();
+    let mut trees: Vec<Vec<u8>> = vec![];
+
+    while let Some(commit_hash) = commits.pop() {
+        if !reachable.insert(hex::encode(&commit_hash)) {
+            continue;
+        }
+        let commit = ioutils::read_commit(objects_path, &commit_hash)?;
+        trees.push(commit.tree_hash);
+        commits.extend(commit.parent_hash);
+    }
+
+    while let Some(tree_hash) = trees.pop() {
+        if !reachable.insert(hex::encode(&tree_hash)) {
+            continue;
+        }
+        for te in ioutils::read_tree(objects_path, &tree_hash)? {
+            match te.is_tree() {
+                true => trees.push(te.sha_hash),
+                false => {
+                    reachable.insert(hex::encode(te.sha_hash));
+                }
+            }
+        }
+    }
+
+    match ioutils::read_index() {
+        Ok((_, ies)) => reachable.extend(ies.into_iter().map(|ie| hex::encode(ie.sha_hash))),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    Ok(reachable)
+}
+
+/// Rewrites the packs holding unreachable objects into a single pack
+/// without them. Packed objects have no mtime of their own, the one of
+/// their pack counts, so objects of packs newer than `cutoff` are kept.
+/// Returns the number of removed and kept objects and the bytes saved.
+fn prune_packs(
+    objects_path: &Path,
+    reachable: &HashSet<String>,
+    cutoff: i64,
+    dry_run: bool,
+) -> io::Result<(usize, usize, u64)> {
+    let (mut kept, mut old_size) = (0, 0);
+    let mut pruned: HashSet<String> = HashSet::new();
+    let mut keep: HashSet<String> = HashSet::new();
+    let mut stale_packs = vec![];
+    for idx_path in packutils::list_pack_indexes(objects_path)? {
+        let pack_md = fs::metadata(idx_path.with_extension("pack"))?;
+        let expired = pack_md.mtime() < cutoff;
+
+        let hashes: Vec<String> = packutils::read_index_hashes(&idx_path)?
+            .into_iter()
+            .map(hex::encode)
+            .collect();
+        let (unreachable, live): (Vec<String>, Vec<String>) =
+            hashes.into_iter().partition(|h| !reachable.contains(h));
+        if unreachable.is_empty() {
+            continue;
+        }
+        if !expired {
+            kept += unreachable.len();
+            continue;
+        }
+
+        old_size += pack_md.len() + fs::metadata(&idx_path)?.len();
+        pruned.extend(unreachable);
+        keep.extend(live);
+        stale_packs.push(idx_path);
+    }
+
+    let mut pruned: Vec<String> = pruned.into_iter().collect();
+    pruned.sort();
+    if dry_run {
+        for file_hash in pruned.iter() {
+            println!("would remove {}", file_hash);
+        }
+        return Ok((pruned.len(), kept, 0));
+    }
+    if stale_packs.is_empty() {
+        return Ok((0, kept, 0));
+    }
+
+    let mut objects = vec![];
+    for file_hash in keep {
+        let raw = ioutils::read_raw_object(objects_path, &file_hash)?;
+        let hash = hex::decode(&file_hash).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        objects.push((hash, raw));
+    }
+    let mut new_size = 0;
+    let mut new_idx_path = None;
+    if !objects.is_empty() {
+        let stats = packutils::write_pack(objects_path, objects)?;
+        new_size = stats.size + fs::metadata(stats.pack_path.with_extension("idx"))?.len();
+        new_idx_path = Some(stats.pack_path.with_extension("idx"));
+    }
+
+    for idx_path in stale_packs {
+        if Some(&idx_path) == new_idx_path.as_ref() {
+            continue;
+        }
+        // index first, a pack without one is never read
+        fs::remove_file(&idx_path)?;
+        fs::remove_file(idx_path.with_extension("pack"))?;
+    }
+    for file_hash in pruned.iter() {
+        println!("removed {}", file_hash);
+    }
+
+    Ok((pruned.len(), kept, old_size.saturating_sub(new_size)))
+}
+
+/// Removes loose and packed objects that are unreachable and older
+/// than `prune_expire` (default: `gc.pruneExpire`, then 2 weeks).
+/// With `dry_run` only reports what would be removed.
+pub fn gc_rit(dry_run: bool, prune_expire: Option<&str>) -> RitResult<()> {
+    let objects_path = ioutils::get_objects_path()?;
+
+    let expire = match prune_expire {
+        Some(e) => e.to_string(),
+        None => configutils::get_config_value("gc.pruneExpire")?
+            .unwrap_or_else(|| DEFAULT_PRUNE_EXPIRE.to_string()),
+    };
+    let cutoff = timeutils::parse_expiry(&expire, timeutils::now()).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid prune expiry: {}", expire),
+        )
+    })?;
+
+    let reachable = reachable_objects(&objects_path)?;
+
+    let (mut removed, mut kept, mut bytes) = (0, 0, 0);
+    for (file_hash, path) in ioutils::list_loose_objects(&objects_path)? {
+        if reachable.contains(&file_hash) {
+            continue;
+        }
+
+        let md = fs::metadata(&path)?;
+        if md.mtime() >= cutoff {
+            kept += 1;
+            continue;
+        }
+
+        if dry_run {
+            println!("would remove {}", file_hash);
+        } else {
+            ioutils::delete_file_hash(&objects_path, &file_hash)?;
+            if let Some(folder) = path.parent() {
+                // only succeeds once the folder is empty
+                let _ = fs::remove_dir(folder);
+            }
+            println!("removed {}", file_hash);
+        }
+        removed += 1;
+        bytes += md.len();
+    }
+
+    let (packed_removed, packed_kept, packed_bytes) =
+        prune_packs(&objects_path, &reachable, cutoff, dry_run)?;
+    removed += packed_removed;
+    kept += packed_kept;
+    bytes += packed_bytes;
+
+    match dry_run {
+        true => println!(
+            "would remove {} unreachable objects, reclaiming {} bytes",
+            removed, bytes
+        ),
+        false => println!(
+            "removed {} unreachable objects, reclaimed {} bytes",
+            removed, bytes
+        ),
+    }
+    if kept > 0 {
+        println!("kept {} unreachable objects newer than {}", kept, expire);
+    }
+
+    Ok(())
+}
@@ -1,20 +1,33 @@
-use std::io::{self, Error, ErrorKind};
-use std::{fs, path::Path};
+use std::fs;
+use std::io::{Error, ErrorKind};
 
-pub fn init_rit() -> io::Result<()> {
-    let parent_dir = Path::new(".rit");
+use crate::models::{errormodels::RitResult, refmodels::Head};
+use crate::repository;
+use crate::utils::{configutils, refutils};
 
-    if parent_dir.exists() {
+pub fn init_rit() -> RitResult<()> {
+    let rit_dir = repository::rit_dir();
+
+    if rit_dir.exists() {
         return Err(Error::new(
             ErrorKind::AlreadyExists,
             "rit already initialized in this dir",
-        ));
+        )
+        .into());
     };
 
-    fs::create_dir_all(".rit/objects")?;
-    fs::create_dir(".rit/hooks")?;
-    fs::create_dir(".rit/info")?;
-    fs::create_dir(".rit/logs")?;
-    fs::create_dir(".rit/refs")?;
-    fs::create_dir(".rit/rr-cache")
+    fs::create_dir_all(rit_dir.join("objects"))?;
+    fs::create_dir(rit_dir.join("hooks"))?;
+    fs::create_dir(rit_dir.join("info"))?;
+    fs::create_dir(rit_dir.join("logs"))?;
+    fs::create_dir_all(rit_dir.join("refs").join("heads"))?;
+    fs::create_dir(rit_dir.join("rr-cache"))?;
+
+    // init.defaultBranch can only come from the global or system
+    // config since the repository config does not exist yet
+    let branch = configutils::get_config_value("init.defaultBranch")?
+        .filter(|b| refutils::is_valid_branch_name(b))
+        .unwrap_or_else(|| refutils::DEFAULT_BRANCH.to_string());
+
+    Ok(refutils::write_head(&Head::Branch(branch))?)
 }
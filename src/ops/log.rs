@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    models::{
+        commitmodels::{Commit, Signature},
+        errormodels::{RitError, RitResult},
+    },
+    utils::{colorutils, ioutils, pathutils, refutils, timeutils},
+};
+
+/// Maps commit hashes to the branch names pointing at them.
+fn get_decorations() -> io::Result<HashMap<Vec<u8>, Vec<String>>> {
+    let mut decorations: HashMap<Vec<u8>, Vec<String>> = HashMap::new();
+    for branch in refutils::list_branches()? {
+        if let Some(hash) = refutils::read_branch(&branch)? {
+            decorations.entry(hash).or_default().push(branch);
+        }
+    }
+    Ok(decorations)
+}
+
+fn format_decoration(
+    names: Option<&Vec<String>>,
+    is_head: bool,
+    current: &Option<String>,
+) -> String {
+    let mut labels: Vec<String> = vec![];
+    if is_head && current.is_none() {
+        labels.push("HEAD".to_string());
+    }
+    for name in names.into_iter().flatten() {
+        if is_head && current.as_ref() == Some(name) {
+            labels.insert(0, format!("HEAD -> {}", name));
+        } else {
+            labels.push(name.clone());
+        }
+    }
+
+    match labels.is_empty() {
+        true => String::new(),
+        false => format!(" ({})", labels.join(", ")),
+    }
+}
+
+/// Blob hashes of all tree entries that fall under one of the filters.
+fn filtered_entries(
+    objects_path: &Path,
+    commit: &Commit,
+    filters: &[String],
+) -> io::Result<HashMap<String, Vec<u8>>> {
+    Ok(
+        ioutils::read_tree_recursive(objects_path, &commit.tree_hash)?
+            .into_iter()
+            .filter(|te| {
+                filters
+                    .iter()
+                    .any(|f| pathutils::is_under(&te.file_path, f))
+            })
+            .map(|te| (te.file_path, te.sha_hash))
+            .collect(),
+    )
+}
+
+/// A commit touches the filtered paths if their content
+/// differs from the parent (or they exist in a root commit).
+fn touches_paths(objects_path: &Path, commit: &Commit, filters: &[String]) -> io::Result<bool> {
+    let entries = filtered_entries(objects_path, commit, filters)?;
+    match &commit.parent_hash {
+        Some(parent_hash) => {
+            let parent = ioutils::read_commit(objects_path, parent_hash)?;
+            Ok(entries != filtered_entries(objects_path, &parent, filters)?)
+        }
+        None => Ok(!entries.is_empty()),
+    }
+}
+
+fn print_commit(commit_hash: &[u8], commit: &Commit, decoration: &str, oneline: bool) {
+    let hash = hex::encode(commit_hash);
+    if oneline {
+        println!(
+            "{}{} {}",
+            colorutils::paint(&hash[..7], colorutils::YELLOW),
+            decoration,
+            commit.message.lines().next().unwrap_or("")
+        );
+        return;
+    }
+
+    let author = Signature::parse(&commit.author);
+    println!(
+        "{}{}",
+        colorutils::paint(&format!("commit {}", hash), colorutils::YELLOW),
+        decoration
+    );
+    println!("Author: {} <{}>", author.name, author.email);
+    if let (Some(ts), Some(tz)) = (author.timestamp, &author.timezone) {
+        println!("Date:   {}", timeutils::format_timestamp(ts, tz));
+    }
+    println!();
+    for line in commit.message.lines() {
+        println!("    {}", line);
+    }
+    println!();
+}
+
+/// Walks the history from HEAD through the parents of each commit.
+/// - `max_count` stops after that many commits have been collected
+/// - `paths` limits the result to commits changing those paths
+pub fn read_log(max_count: Option<usize>, paths: &[PathBuf]) -> RitResult<Vec<(Vec<u8>, Commit)>> {
+    let objects_path = ioutils::get_objects_path()?;
+    let filters: Vec<String> = paths.iter().map(|p| pathutils::to_rit_path(p)).collect();
+
+    let mut commits = vec![];
+    let mut next = refutils::resolve_head()?;
+    while let Some(commit_hash) = next {
+        if max_count.is_some_and(|max| commits.len() >= max) {
+            break;
+        }
+
+        let commit = ioutils::read_commit(&objects_path, &commit_hash)?;
+        next = commit.parent_hash.clone();
+
+        if filters.is_empty() || touches_paths(&objects_path, &commit, &filters)? {
+            commits.push((commit_hash, commit));
+        }
+    }
+
+    Ok(commits)
+}
+
+/// Shows the history of HEAD as read by `read_log`.
+pub fn print_log(commits: &[(Vec<u8>, Commit)], oneline: bool) -> RitResult<()> {
+    let head_hash = match refutils::resolve_head()? {
+        Some(h) => h,
+        None => {
+            return Err(RitError::NotFound(
+                "HEAD does not point to any commit yet".to_string(),
+            ))
+        }
+    };
+
+    let decorations = get_decorations()?;
+    let current = refutils::current_branch()?;
+
+    for (commit_hash, commit) in commits.iter() {
+        let decoration = format_decoration(
+            decorations.get(commit_hash),
+            *commit_hash == head_hash,
+            &current,
+        );
+        print_commit(commit_hash, commit, &decoration, oneline);
+    }
+
+    Ok(())
+}
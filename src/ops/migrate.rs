@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use crate::models::{
+    commitmodels::Commit,
+    errormodels::{RitError, RitResult},
+    objectmodels::{decode_object, ObjectKind},
+    refmodels::Head,
+    treemodels::{parse_tree, serialize_tree},
+};
+use crate::utils::{hashutils::get_hash_from_file, ioutils, refutils};
+
+/// Rewrites the objects of a repository created by an older rit.
+/// Old objects have neither an envelope nor compression, so they get
+/// new hashes, and so do the trees and commits referring to them.
+struct Migration<'a> {
+    objects_path: &'a Path,
+    /// old hash -> new hash of every object visited
+    migrated: HashMap<Vec<u8>, Vec<u8>>,
+    /// old objects to remove once nothing refers to them anymore
+    legacy: Vec<String>,
+}
+
+impl Migration<'_> {
+    /// Content of an object in either format. Old objects do not
+    /// know their kind, it is the one they are referred to as.
+    fn read(&mut self, hash: &[u8], kind: ObjectKind) -> io::Result<Vec<u8>> {
+        let file_hash = hex::encode(hash);
+        let corrupt = |reason: &str| -> io::Error {
+            RitError::CorruptObject {
+                hash: file_hash.clone(),
+                reason: reason.to_string(),
+            }
+            .into()
+        };
+
+        let object = match ioutils::read_loose_object(self.objects_path, &file_hash)? {
+            Some(stored) if ioutils::is_legacy_object(&stored, &file_hash) => {
+                self.legacy.push(file_hash.clone());
+                return Ok(stored);
+            }
+            Some(stored) => ioutils::decompress(&stored).map_err(|e| corrupt(&e.to_string()))?,
+            None => ioutils::read_raw_object(self.objects_path, &file_hash)?,
+        };
+
+        if get_hash_from_file(&object).0 != file_hash {
+            return Err(corrupt("hash does not match its content"));
+        }
+        let (found, content) = decode_object(&object).map_err(|e| corrupt(&e.to_string()))?;
+        if found != kind {
+            return Err(corrupt(&format!("is a {}, not a {}", found, kind)));
+        }
+
+        Ok(content.to_vec())
+    }
+
+    fn write(&mut self, hash: &[u8], kind: ObjectKind, content: &[u8]) -> io::Result<Vec<u8>> {
+        let (_, new_hash) = ioutils::write_object(self.objects_path, kind, content)?;
+        self.migrated.insert(hash.to_vec(), new_hash.clone());
+        Ok(new_hash)
+    }
+
+    /// Migrates a blob or a tree and everything below it.
+    fn migrate_object(&mut self, hash: &[u8], kind: ObjectKind) -> io::Result<Vec<u8>> {
+        if let Some(new_hash) = self.migrated.get(hash) {
+            return Ok(new_hash.clone());
+        }
+
+        let content = match self.read(hash, kind) {
+            Ok(content) => content,
+            Err(e) => match RitError::from(e) {
+                // older rit deleted the blob of a file on every re-add,
+                // even when an earlier commit still referred to it
+                RitError::MissingObject(file_hash) if kind == ObjectKind::Blob => {
+                    eprintln!("warning: blob {} is missing, keeping its hash", file_hash);
+                    self.migrated.insert(hash.to_vec(), hash.to_vec());
+                    return Ok(hash.to_vec());
+                }
+                e => return Err(e.into()),
+            },
+        };
+        let content = match kind {
+            ObjectKind::Tree => {
+                let mut entries = parse_tree(&content)?;
+                for te in entries.iter_mut() {
+                    let kind = match te.is_tree() {
+                        true => ObjectKind::Tree,
+                        false => ObjectKind::Blob,
+                    };
+                    te.sha_hash = self.migrate_object(&te.sha_hash, kind)?;
+                }
+                serialize_tree(&entries)
+            }
+            _ => content,
+        };
+
+        self.write(hash, kind, &content)
+    }
+
+    /// Old objects nothing refers to have no kind to go by, it is
+    /// told from their content: a commit refers to objects that
+    /// exist, tree entries have the mode of a file or a dir.
+    fn leftover_kind(&self, content: &[u8]) -> ObjectKind {
+        let exists = |hash: &[u8]| ioutils::object_exists(self.objects_path, &hex::encode(hash));
+        let is_commit = Commit::from_bytes(content)
+            .is_ok_and(|c| exists(&c.tree_hash) && c.parent_hash.as_deref().is_none_or(exists));
+        if is_commit {
+            return ObjectKind::Commit;
+        }
+
+        let is_tree = parse_tree(content).is_ok_and(|entries| {
+            !entries.is_empty()
+                && entries.iter().all(|te| {
+                    !te.file_path.is_empty() && matches!(te.mode & 0o170000, 0o100000 | 0o040000)
+                })
+        });
+        match is_tree {
+            true => ObjectKind::Tree,
+            false => ObjectKind::Blob,
+        }
+    }
+
+    /// Migrates a commit and its history, oldest first so every
+    /// parent has its new hash before its child is rewritten.
+    fn migrate_commit(&mut self, hash: &[u8]) -> io::Result<Vec<u8>> {
+        let mut history = vec![];
+        let mut next = Some(hash.to_vec());
+        while let Some(hash) = next.filter(|h| !self.migrated.contains_key(h)) {
+            let content = self.read(&hash, ObjectKind::Commit)?;
+            next = Commit::from_bytes(&content)?.parent_hash;
+            history.push((hash, content));
+        }
+
+        for (hash, content) in history.into_iter().rev() {
+            // the tree and the parent are the first 65 bytes,
+            // see `commit::prepare_commit_content`
+            let mut new_content = self.migrate_object(&content[..32], ObjectKind::Tree)?;
+            new_content.push(content[32]);
+            match content[32] {
+                0 => new_content.extend_from_slice(&content[33..65]),
+                _ => new_content.extend_from_slice(&self.migrated[&content[33..65]]),
+            }
+            new_content.extend_from_slice(&content[65..]);
+
+            self.write(&hash, ObjectKind::Commit, &new_content)?;
+        }
+
+        Ok(self.migrated[hash].clone())
+    }
+}
+
+/// Brings a repository created by an older rit up to date: every
+/// object gets its envelope and is compressed, then the trees, commits,
+/// branches, HEAD and INDEX are rewritten to the new hashes.
+pub fn migrate_rit() -> RitResult<()> {
+    let objects_path = ioutils::get_objects_path()?;
+    let mut migration = Migration {
+        objects_path: &objects_path,
+        migrated: HashMap::new(),
+        legacy: vec![],
+    };
+
+    for branch in refutils::list_branches()? {
+        if let Some(hash) = refutils::read_branch(&branch)? {
+            let new_hash = migration.migrate_commit(&hash)?;
+            if new_hash != hash {
+                refutils::write_branch(&branch, &new_hash)?;
+            }
+        }
+    }
+
+    if let Head::Detached(hash) = refutils::read_head()? {
+        let new_hash = migration.migrate_commit(&hash)?;
+        if new_hash != hash {
+            refutils::write_head(&Head::Detached(new_hash))?;
+        }
+    }
+
+    let lock = ioutils::lock_index()?;
+    match ioutils::read_index() {
+        Ok((header, mut entries)) => {
+            for ie in entries.iter_mut() {
+                ie.sha_hash = migration.migrate_object(&ie.sha_hash, ObjectKind::Blob)?;
+            }
+            ioutils::write_index_locked(lock, header, entries)?;
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    // nothing refers to the remaining old objects, e.g. blobs of
+    // files added and replaced before the next commit
+    for (file_hash, _) in ioutils::list_loose_objects(&objects_path)? {
+        let hash = hex::decode(&file_hash).expect("listed hashes are hex");
+        if !migration.migrated.contains_key(&hash) {
+            let stored = ioutils::read_loose_object(&objects_path, &file_hash)?;
+            let stored = match stored {
+                Some(s) if ioutils::is_legacy_object(&s, &file_hash) => s,
+                _ => continue,
+            };
+            match migration.leftover_kind(&stored) {
+                ObjectKind::Commit => migration.migrate_commit(&hash)?,
+                kind => migration.migrate_object(&hash, kind)?,
+            };
+        }
+    }
+
+    let mut legacy = migration.legacy;
+    legacy.sort();
+    legacy.dedup();
+    for file_hash in legacy.iter() {
+        ioutils::delete_file_hash(&objects_path, file_hash)?;
+    }
+
+    match legacy.len() {
+        0 => println!("** All objects are already up to date **"),
+        n => println!("migrated {} objects", n),
+    }
+
+    Ok(())
+}
@@ -1,4 +1,14 @@
 pub mod add;
+pub mod branch;
+pub mod checkout;
 pub mod commit;
+pub mod config;
+pub mod diff;
+pub mod fsck;
+pub mod gc;
 pub mod init;
+pub mod log;
+pub mod migrate;
+pub mod repack;
+pub mod rm;
 pub mod status;
@@ -0,0 +1,71 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+};
+
+use crate::models::errormodels::RitResult;
+use crate::utils::{hashutils::get_hash_from_file, ioutils, packutils};
+
+/// Moves all loose objects and existing packs into a single new pack,
+/// then removes what it replaced. Every object is rehashed first so a
+/// corrupt object never ends up in a pack.
+pub fn repack_rit() -> RitResult<()> {
+    let objects_path = ioutils::get_objects_path()?;
+
+    let loose = ioutils::list_loose_objects(&objects_path)?;
+    let old_indexes = packutils::list_pack_indexes(&objects_path)?;
+    if loose.is_empty() && old_indexes.len() <= 1 {
+        println!("** Nothing to repack **");
+        return Ok(());
+    }
+
+    let mut file_hashes: Vec<String> = loose.iter().map(|(h, _)| h.clone()).collect();
+    file_hashes.extend(packutils::list_packed_objects(&objects_path)?);
+    file_hashes.sort();
+    file_hashes.dedup();
+
+    let mut objects = vec![];
+    for file_hash in file_hashes {
+        let raw = ioutils::read_raw_object(&objects_path, &file_hash)?;
+        let (actual_hash, hash_vec) = get_hash_from_file(&raw);
+        if actual_hash != file_hash {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "object {} is corrupt, it hashes to {}",
+                    file_hash, actual_hash
+                ),
+            )
+            .into());
+        }
+        objects.push((hash_vec, raw));
+    }
+
+    let stats = packutils::write_pack(&objects_path, objects)?;
+
+    for idx_path in old_indexes {
+        if idx_path == stats.pack_path.with_extension("idx") {
+            continue;
+        }
+        // index first, a pack without one is never read
+        fs::remove_file(&idx_path)?;
+        fs::remove_file(idx_path.with_extension("pack"))?;
+    }
+    for (file_hash, path) in loose {
+        ioutils::delete_file_hash(&objects_path, &file_hash)?;
+        if let Some(folder) = path.parent() {
+            // only succeeds once the folder is empty
+            let _ = fs::remove_dir(folder);
+        }
+    }
+
+    println!(
+        "packed {} objects ({} as deltas) into {}, {} bytes",
+        stats.objects,
+        stats.deltas,
+        stats.pack_path.display(),
+        stats.size
+    );
+
+    Ok(())
+}
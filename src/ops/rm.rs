@@ -0,0 +1,120 @@
+use std::{
+    collections::BTreeSet,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+use crate::models::errormodels::{RitError, RitResult};
+use crate::utils::{ioutils, pathutils, refutils};
+
+/// Index entries matched by `path`. Without `recursive`
+/// only the file itself matches, dirs are refused.
+fn matching_entries<'a>(
+    file_paths: &'a BTreeSet<String>,
+    path: &str,
+    recursive: bool,
+) -> RitResult<Vec<&'a String>> {
+    let matches: Vec<&String> = file_paths
+        .iter()
+        .filter(|fp| pathutils::is_under(fp, path))
+        .collect();
+
+    if matches.is_empty() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("pathspec '{}' did not match any tracked files", path),
+        )
+        .into());
+    }
+
+    if !recursive {
+        return match matches.into_iter().find(|fp| fp.as_str() == path) {
+            Some(fp) => Ok(vec![fp]),
+            None => Err(RitError::InvalidInput(format!(
+                "not removing '{}' recursively without -r",
+                path
+            ))),
+        };
+    }
+
+    Ok(matches)
+}
+
+/// Removes files from the INDEX, so their deletion is part of
+/// the next commit, and from the working tree unless `cached`.
+/// Files whose content is not committed yet are kept unless `force`,
+/// as removing them would lose changes rit has no other copy of.
+pub fn rm_rit(paths: Vec<PathBuf>, cached: bool, recursive: bool, force: bool) -> RitResult<()> {
+    let objects_path = ioutils::get_objects_path()?;
+
+    let lock = ioutils::lock_index()?;
+    let (mut header, mut index_entries) = match ioutils::read_index() {
+        Ok(res) => res,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Err(Error::new(ErrorKind::NotFound, "nothing is tracked yet").into())
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let tracked: BTreeSet<String> = index_entries
+        .iter()
+        .map(|ie| ie.file_path.clone())
+        .collect();
+    let mut to_remove: BTreeSet<String> = BTreeSet::new();
+    for path in paths.iter() {
+        let rit_path = pathutils::to_rit_path(path);
+        for fp in matching_entries(&tracked, &rit_path, recursive)? {
+            to_remove.insert(fp.clone());
+        }
+    }
+
+    if !force {
+        let head_tree = ioutils::get_tree_map(&objects_path, refutils::resolve_head()?.as_ref())?;
+
+        let mut problems = vec![];
+        for ie in index_entries
+            .iter()
+            .filter(|ie| to_remove.contains(&ie.file_path))
+        {
+            let staged = head_tree
+                .get(&ie.file_path)
+                .is_none_or(|te| te.sha_hash != ie.sha_hash);
+            let modified =
+                ioutils::get_worktree_hash(&ie.file_path)?.is_some_and(|h| h != ie.sha_hash);
+
+            match (staged, modified) {
+                (true, true) => problems.push(format!(
+                    "{} has staged content different from both the file and HEAD",
+                    ie.file_path
+                )),
+                (true, false) if !cached => {
+                    problems.push(format!("{} has changes staged in the INDEX", ie.file_path))
+                }
+                (false, true) if !cached => {
+                    problems.push(format!("{} has local modifications", ie.file_path))
+                }
+                _ => {}
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(RitError::InvalidInput(format!(
+                "{}\n\nuse --cached to keep the file, or -f to force removal",
+                problems.join("\n")
+            )));
+        }
+    }
+
+    index_entries.retain(|ie| !to_remove.contains(&ie.file_path));
+    header.set_num_entries(index_entries.len() as u32);
+    ioutils::write_index_locked(lock, header, index_entries)?;
+
+    for file_path in to_remove.iter() {
+        if !cached {
+            ioutils::remove_worktree_file(file_path)?;
+        }
+        println!("rm '{}'", file_path);
+    }
+
+    Ok(())
+}
@@ -1,172 +1,357 @@
 use crate::{
-    models::indexmodels::IndexEntry,
-    utils::{hashutils::get_hash_from_file, ioutils},
+    models::{
+        errormodels::RitResult,
+        indexmodels::{IndexEntry, IndexHeader},
+        objectmodels::ObjectKind,
+        refmodels::Head,
+        statusmodels::{ChangeKind, FileChange, Status, StatusFormat},
+    },
+    repository,
+    utils::{colorutils, hashutils::hash_object, ioutils, jsonutils, pathutils, refutils},
 };
 use std::{
-    collections::HashMap,
-    fs::{self, read},
-    io,
-    path::{Path, PathBuf},
+    collections::{BTreeMap, HashSet},
+    fs,
+    io::{self, ErrorKind},
+    os::unix::fs::MetadataExt,
 };
 
-fn get_head_commit_path(objects_path: &Path) -> io::Result<PathBuf> {
-    let main_file = Path::new("./.rit/refs/heads/main");
-    let commit_hash = hex::encode(fs::read(main_file)?);
+/// INDEX vs the tree of HEAD. A path deleted from HEAD and a new
+/// path with the exact same content are reported as a rename.
+fn staged_changes(
+    head_tree: &BTreeMap<String, (u32, Vec<u8>)>,
+    index: &BTreeMap<String, IndexEntry>,
+) -> Vec<FileChange> {
+    let mut changes = vec![];
+    let mut added: Vec<&IndexEntry> = vec![];
+    let mut deleted: Vec<(&String, &Vec<u8>)> = vec![];
 
-    let commit_dir = &commit_hash[..3];
-    let commit_filename = &commit_hash[3..];
+    for (file_path, ie) in index.iter() {
+        match head_tree.get(file_path) {
+            None => added.push(ie),
+            Some((mode, hash)) if *hash != ie.sha_hash || *mode != ie.mode => {
+                changes.push(FileChange {
+                    kind: ChangeKind::Modified,
+                    file_path: file_path.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for (file_path, (_, hash)) in head_tree.iter() {
+        if !index.contains_key(file_path) {
+            deleted.push((file_path, hash));
+        }
+    }
 
-    Ok(Path::new(&objects_path)
-        .join(commit_dir)
-        .join(commit_filename))
+    let mut renamed: HashSet<&String> = HashSet::new();
+    for (from, hash) in deleted {
+        let target = added
+            .iter()
+            .find(|ie| ie.sha_hash == *hash && !renamed.contains(&ie.file_path));
+        let change = match target {
+            Some(ie) => {
+                renamed.insert(&ie.file_path);
+                FileChange {
+                    kind: ChangeKind::Renamed { from: from.clone() },
+                    file_path: ie.file_path.clone(),
+                }
+            }
+            None => FileChange {
+                kind: ChangeKind::Deleted,
+                file_path: from.clone(),
+            },
+        };
+        changes.push(change);
+    }
+    for ie in added {
+        if !renamed.contains(&ie.file_path) {
+            changes.push(FileChange {
+                kind: ChangeKind::New,
+                file_path: ie.file_path.clone(),
+            });
+        }
+    }
+
+    changes.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    changes
 }
 
-fn retrieve_committed_content() -> io::Result<HashMap<PathBuf, Vec<u8>>> {
-    let objects_path = ioutils::get_objects_path()?;
-    let commit_path = get_head_commit_path(&objects_path)?;
+/// Working tree vs INDEX. Every tracked file is checked,
+/// including the ones an ignore rule would hide.
+///
+/// Files whose stat data matches their entry are not read at all.
+/// The others are hashed, and when the content turns out to be the
+/// same the entry gets the new stat data so the next run skips them.
+/// Returns whether INDEX needs to be written back.
+fn unstaged_changes(
+    index: &mut BTreeMap<String, IndexEntry>,
+    index_mtime: (u32, u32),
+) -> io::Result<(Vec<FileChange>, bool)> {
+    let mut changes = vec![];
+    let mut refreshed = false;
 
-    let commit_content = fs::read(commit_path)?;
+    for (file_path, ie) in index.iter_mut() {
+        let worktree_path = repository::work_tree_path(file_path);
+        // a dir in place of the file, or a file in place of
+        // one of its dirs, means the file itself is gone
+        let md = match fs::metadata(&worktree_path) {
+            Ok(md) if !md.is_dir() => Some(md),
+            Ok(_) => None,
+            Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) => None,
+            Err(e) => return Err(e),
+        };
+        let md = match md {
+            Some(md) => md,
+            None => {
+                changes.push(FileChange {
+                    kind: ChangeKind::Deleted,
+                    file_path: file_path.clone(),
+                });
+                continue;
+            }
+        };
 
-    // hashed version of SHA256, hence 32 bytes
-    let tree_hash = hex::encode(&commit_content[..32]);
-    if tree_hash.is_empty() {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "unable to find tree hash in commit file",
-        ));
+        if ie.matches_metadata(&md) && !ie.is_racy(index_mtime) {
+            continue;
+        }
+
+        // a changed mode is a worktree change too, refreshing
+        // below must never stage it
+        let changed = ie.mode != md.mode()
+            || ie.size != md.size() as u32
+            || hash_object(ObjectKind::Blob, &fs::read(&worktree_path)?).1 != ie.sha_hash;
+        if changed {
+            changes.push(FileChange {
+                kind: ChangeKind::Modified,
+                file_path: file_path.clone(),
+            });
+        } else {
+            // rewriting INDEX also moves its mtime past racy entries
+            *ie = IndexEntry::from_metadata(file_path.clone(), ie.sha_hash.clone(), &md);
+            refreshed = true;
+        }
     }
 
-    let tree_path = Path::new(&objects_path)
-        .join(&tree_hash[..3])
-        .join(&tree_hash[3..]);
-    let tree_content = fs::read(tree_path)?;
+    Ok((changes, refreshed))
+}
+
+/// Compares HEAD, INDEX and the working tree, see `Status`.
+pub fn get_status() -> RitResult<Status> {
+    let objects_path = ioutils::get_objects_path()?;
+
+    let head_tree: BTreeMap<String, (u32, Vec<u8>)> =
+        ioutils::get_tree_map(&objects_path, refutils::resolve_head()?.as_ref())?
+            .into_iter()
+            .map(|(file_path, te)| (file_path, (te.mode, te.sha_hash)))
+            .collect();
+
+    // without the lock, e.g. in a read-only repository or while
+    // another rit changes INDEX, the stat data is not refreshed
+    let lock = ioutils::lock_index().ok();
+    let (header, mut index): (Option<IndexHeader>, BTreeMap<String, IndexEntry>) =
+        match ioutils::read_index() {
+            Ok((header, ies)) => (
+                Some(header),
+                ies.into_iter()
+                    .map(|ie| (ie.file_path.clone(), ie))
+                    .collect(),
+            ),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (None, BTreeMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+    let index_mtime = match fs::metadata(ioutils::get_index_path()) {
+        Ok(md) => (md.mtime() as u32, md.mtime_nsec() as u32),
+        Err(_) => (0, 0),
+    };
 
-    let mut committed_content: HashMap<PathBuf, Vec<u8>> = HashMap::new();
-    let mut pos: usize = 0;
-    while pos < tree_content.len() - 1 {
-        // let mode = &tree_content[pos..pos+4];
-        let file_path_len_bytes: [u8; 4] = tree_content[pos + 4..pos + 8]
-            .try_into()
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-        let file_path_len = u32::from_be_bytes(file_path_len_bytes) as usize;
-        let file_path = String::from_utf8_lossy(&tree_content[pos + 8..8 + pos + file_path_len]);
-        pos = 8 + pos + file_path_len;
+    let staged = staged_changes(&head_tree, &index);
+    let (unstaged, refreshed) = unstaged_changes(&mut index, index_mtime)?;
 
-        let file_hash = &tree_content[pos..pos + 32];
-        pos += 32;
-        committed_content.insert(PathBuf::from(file_path.into_owned()), file_hash.to_owned());
+    let untracked = ioutils::get_all_paths()
+        .iter()
+        .map(|p| pathutils::to_rit_path(p))
+        .filter(|p| !index.contains_key(p))
+        .collect();
 
-        pos = (pos + 7) & !7;
+    // best effort, status still works if INDEX cannot be written
+    if let (Some(lock), Some(header), true) = (lock, header, refreshed) {
+        let _ = ioutils::write_index_locked(lock, header, index.into_values().collect());
     }
 
-    Ok(committed_content)
+    Ok(Status {
+        staged,
+        unstaged,
+        untracked,
+    })
 }
 
-/// To check the status, we need to consider serveral factors
-/// 1. Fetch all paths in current dir excluding .gitignore
-/// 2. Check the INDEX to see what's been added
-/// 3. Extract paths that are not part of INDEX
-/// 4. Check if the files added have been changed (based on hash)
-/// 5. Show the added paths as green, untracked paths as red, and modified as yellow
-pub fn status_rit() {
-    if !Path::new(".rit").exists() {
-        eprintln!("rit has not been initialized in this dir!\n\nrun this command:\n> rit init");
-        return;
-    }
-
-    let all_paths = ioutils::get_all_paths();
-
-    let committed_content;
-    let mut check_commited = true;
-    match retrieve_committed_content() {
-        Ok(cc) => committed_content = cc,
-        Err(e) => {
-            if e.kind() != io::ErrorKind::NotFound {
-                eprintln!("Error reading committed content: {}", e);
-                return;
-            } else {
-                committed_content = HashMap::new();
-                check_commited = false;
-            }
-        }
+fn print_section(title: &str, hints: &[&str], lines: &[String], color: &str) {
+    println!("{}", title);
+    for hint in hints {
+        println!("  ({})", hint);
     }
-
-    let mut untracked: Vec<PathBuf> = Vec::new();
-    let mut modifed_unstaged: Vec<PathBuf> = Vec::new();
-    let mut staged_uncommitted: Vec<PathBuf> = Vec::new();
-    // let mut deleted = Vec::new();
-
-    let mut index_entries: HashMap<PathBuf, IndexEntry> = HashMap::new();
-    match ioutils::read_index() {
-        Ok((_, ies)) => ies.into_iter().for_each(|ie| {
-            index_entries.insert(PathBuf::from(&ie.file_path), ie);
-        }),
-        Err(e) => {
-            if e.kind() != io::ErrorKind::NotFound {
-                eprintln!("{}", e);
-                return;
-            }
-        }
+    for line in lines {
+        println!("\t{}", colorutils::paint(line, color));
     }
+    println!();
+}
 
-    all_paths.iter().for_each(|p| {
-        let content = read(p).unwrap();
-        let (_, hash_vec) = get_hash_from_file(&content);
+/// Paths without the "./" prefix of INDEX and trees.
+fn display_path(file_path: &str) -> &str {
+    file_path.strip_prefix("./").unwrap_or(file_path)
+}
 
-        // 1. Check if committed, No action required for these
-        if check_commited {
-            if let Some(h) = committed_content.get(p) {
-                if *h == hash_vec {
-                    return;
-                }
-            }
+/// Paths with quotes, backslashes or control characters are
+/// quoted C-style, so every porcelain line can be split safely.
+fn porcelain_path(file_path: &str) -> String {
+    let path = display_path(file_path);
+    if !path
+        .chars()
+        .any(|c| c == '"' || c == '\\' || c.is_control())
+    {
+        return path.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    for c in path.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\{:03o}", c as u32)),
+            c => quoted.push(c),
         }
+    }
+    quoted.push('"');
+    quoted
+}
 
-        // 2 & 3. Check if staged modified or uncommitted
-        if let Some(entry) = index_entries.get(p) {
-            if hash_vec != entry.sha_hash {
-                modifed_unstaged.push(p.to_path_buf())
-            } else {
-                staged_uncommitted.push(p.to_path_buf());
-            }
-            return;
+/// "XY <path>" per entry, "XY <from> -> <path>" for renames,
+/// see `StatusEntry::xy`.
+fn print_porcelain(status: &Status) {
+    for entry in status.entries() {
+        match entry.index {
+            Some(ChangeKind::Renamed { from }) => println!(
+                "{} {} -> {}",
+                entry.xy(),
+                porcelain_path(from),
+                porcelain_path(entry.file_path)
+            ),
+            _ => println!("{} {}", entry.xy(), porcelain_path(entry.file_path)),
         }
+    }
+}
 
-        // 4. Collect untracked
-        untracked.push(p.to_path_buf());
-    });
+fn json_state(kind: Option<&ChangeKind>) -> &'static str {
+    match kind {
+        None => "unmodified",
+        Some(ChangeKind::New) => "added",
+        Some(ChangeKind::Modified) => "modified",
+        Some(ChangeKind::Deleted) => "deleted",
+        Some(ChangeKind::Renamed { .. }) => "renamed",
+    }
+}
+
+/// A single JSON object:
+/// {"branch": .., "head": .., "files": [{"path", "orig_path", "index", "worktree"}]}
+/// where branch is null when detached and head is null before the first commit.
+fn print_json(status: &Status) -> RitResult<()> {
+    let branch = match refutils::read_head()? {
+        Head::Branch(name) => Some(name),
+        Head::Detached(_) => None,
+    };
+    let head = refutils::resolve_head()?.map(hex::encode);
 
-    let mut is_everything_updated = true;
+    let files: Vec<String> = status
+        .entries()
+        .iter()
+        .map(|entry| {
+            let orig_path = match entry.index {
+                Some(ChangeKind::Renamed { from }) => Some(display_path(from)),
+                _ => None,
+            };
+            let (index, worktree) = match entry.is_untracked() {
+                true => ("untracked", "untracked"),
+                false => (json_state(entry.index), json_state(entry.worktree)),
+            };
+            format!(
+                "{{\"path\":{},\"orig_path\":{},\"index\":{},\"worktree\":{}}}",
+                jsonutils::quote(display_path(entry.file_path)),
+                jsonutils::quote_opt(orig_path),
+                jsonutils::quote(index),
+                jsonutils::quote(worktree)
+            )
+        })
+        .collect();
 
-    if !untracked.is_empty() {
-        is_everything_updated = false;
-        println!("---------------------------");
-        println!("\u{1b}[1;31mUntracked:\u{1b}[0m");
-        println!("To add the file:\n>> rit add <PATH>...\n");
-        for up in untracked {
-            println!("\t\u{1b}[1;31m*\u{1b}[0m {}", up.display());
+    println!(
+        "{{\"branch\":{},\"head\":{},\"files\":[{}]}}",
+        jsonutils::quote_opt(branch.as_deref()),
+        jsonutils::quote_opt(head.as_deref()),
+        files.join(",")
+    );
+    Ok(())
+}
+
+/// Shows a status from `get_status` in the given format,
+/// the long one shows the checked out branch followed by:
+/// 1. "Changes to be committed": INDEX vs the tree of HEAD
+/// 2. "Changes not staged for commit": working tree vs INDEX
+/// 3. "Untracked files": working tree files not in INDEX
+pub fn print_status(status: &Status, format: StatusFormat) -> RitResult<()> {
+    match format {
+        StatusFormat::Long => {}
+        StatusFormat::Porcelain => {
+            print_porcelain(status);
+            return Ok(());
         }
+        StatusFormat::Json => return print_json(status),
     }
 
-    if !staged_uncommitted.is_empty() {
-        is_everything_updated = false;
-        println!("---------------------------");
-        println!("\u{1b}[1;32mTracked:\u{1b}[0m");
-        for su in staged_uncommitted {
-            println!("\t\u{1b}[1;32m$\u{1b}[0m {}", su.display());
-        }
+    match refutils::read_head()? {
+        Head::Branch(name) => println!("On branch {}\n", name),
+        Head::Detached(hash) => println!("HEAD detached at {}\n", &hex::encode(hash)[..7]),
     }
 
-    if !modifed_unstaged.is_empty() {
-        is_everything_updated = false;
-        println!("---------------------------");
-        println!("\u{1b}[1;33mModified:\u{1b}[0m");
-        for mp in modifed_unstaged {
-            println!("\t\u{1b}[1;33m>\u{1b}[0m {}", mp.display());
-        }
+    if !status.staged.is_empty() {
+        let lines: Vec<String> = status.staged.iter().map(|c| c.to_string()).collect();
+        print_section(
+            "Changes to be committed:",
+            &["use \"rit rm --cached <PATH>...\" to unstage a new file"],
+            &lines,
+            colorutils::GREEN,
+        );
+    }
+
+    if !status.unstaged.is_empty() {
+        let lines: Vec<String> = status.unstaged.iter().map(|c| c.to_string()).collect();
+        print_section(
+            "Changes not staged for commit:",
+            &[
+                "use \"rit add <PATH>...\" to update what will be committed",
+                "use \"rit rm <PATH>...\" to record a deletion",
+            ],
+            &lines,
+            colorutils::RED,
+        );
     }
 
-    if is_everything_updated {
+    if !status.untracked.is_empty() {
+        print_section(
+            "Untracked files:",
+            &["use \"rit add <PATH>...\" to include in what will be committed"],
+            &status.untracked,
+            colorutils::RED,
+        );
+    }
+
+    if status.is_clean() {
         println!("** Everything is up-to-date **")
+    } else if status.staged.is_empty() {
+        println!("nothing added to commit");
     }
+
+    Ok(())
 }
@@ -0,0 +1,233 @@
+use std::{
+    cell::RefCell,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    models::{
+        commitmodels::Commit,
+        errormodels::{RitError, RitResult},
+        objectmodels::Object,
+        statusmodels::Status,
+    },
+    ops::{
+        add::{self, AddOutcome},
+        commit, init, log, status,
+    },
+    utils::{ioutils, refutils},
+};
+
+pub const RIT_DIR_NAME: &str = ".rit";
+
+thread_local! {
+    static ACTIVE: RefCell<Option<Repository>> = const { RefCell::new(None) };
+}
+
+/// Where a repository lives: the work tree with the checked out
+/// files and the rit dir holding objects, refs, INDEX and config.
+/// The rit dir is usually `<work tree>/.rit`.
+///
+/// The methods work on absolute paths and never on the current dir
+/// of the process, paths passed to them are relative to the work tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repository {
+    work_tree: PathBuf,
+    rit_dir: PathBuf,
+}
+
+/// Resolves "." and ".." without touching the file system,
+/// so paths to files that do not exist (anymore) work too.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for comp in path.components() {
+        match comp {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Keeps a repository active for the ops and utils of the current
+/// thread, the previously active one is restored on drop.
+pub struct ActiveRepository {
+    previous: Option<Repository>,
+}
+
+impl Drop for ActiveRepository {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        ACTIVE.with(|active| *active.borrow_mut() = previous);
+    }
+}
+
+impl Repository {
+    /// Both paths should be absolute, see `open` and `discover`.
+    pub fn new(work_tree: PathBuf, rit_dir: PathBuf) -> Self {
+        Self { work_tree, rit_dir }
+    }
+
+    /// Opens the repository whose work tree is `work_tree`.
+    pub fn open(work_tree: impl AsRef<Path>) -> RitResult<Self> {
+        let work_tree = work_tree.as_ref().canonicalize()?;
+        let rit_dir = work_tree.join(RIT_DIR_NAME);
+        if !rit_dir.join("objects").is_dir() {
+            return Err(RitError::NotARepository(work_tree));
+        }
+
+        Ok(Self::new(work_tree, rit_dir))
+    }
+
+    /// Creates a new repository in `work_tree`, creating the dir if needed.
+    pub fn init(work_tree: impl AsRef<Path>) -> RitResult<Self> {
+        fs::create_dir_all(&work_tree)?;
+        let work_tree = work_tree.as_ref().canonicalize()?;
+        let repo = Self::new(work_tree.clone(), work_tree.join(RIT_DIR_NAME));
+
+        let _active = repo.activate();
+        init::init_rit()?;
+
+        Ok(repo)
+    }
+
+    /// Finds the repository `start` belongs to. An explicit `rit_dir`
+    /// (`--rit-dir` or RIT_DIR) is used as is, relative to `start`, with
+    /// its parent as work tree when it is named ".rit" and `start` otherwise.
+    /// Without one, `start` and its parents are searched for a ".rit" dir.
+    pub fn discover(start: &Path, rit_dir: Option<&Path>) -> RitResult<Self> {
+        let start = start.canonicalize()?;
+
+        if let Some(rit_dir) = rit_dir {
+            let rit_dir = start.join(rit_dir);
+            if !rit_dir.join("objects").is_dir() {
+                return Err(RitError::NotARepository(rit_dir));
+            }
+            let rit_dir = rit_dir.canonicalize()?;
+            let work_tree = match rit_dir.file_name() {
+                Some(name) if name == RIT_DIR_NAME => {
+                    rit_dir.parent().unwrap_or(&start).to_path_buf()
+                }
+                _ => start,
+            };
+            return Ok(Self::new(work_tree, rit_dir));
+        }
+
+        for dir in start.ancestors() {
+            let candidate = dir.join(RIT_DIR_NAME);
+            if candidate.is_dir() {
+                return Ok(Self::new(dir.to_path_buf(), candidate));
+            }
+        }
+
+        Err(RitError::NotARepository(start))
+    }
+
+    pub fn work_tree(&self) -> &Path {
+        &self.work_tree
+    }
+
+    pub fn rit_dir(&self) -> &Path {
+        &self.rit_dir
+    }
+
+    /// Turns a path given relative to `cwd` into one relative to the
+    /// root of the work tree, e.g. "../b" from "<root>/a" becomes "b".
+    /// The root itself becomes ".".
+    pub fn to_work_tree_path(&self, cwd: &Path, path: &Path) -> RitResult<PathBuf> {
+        let absolute = normalize(&cwd.join(path));
+        let work_tree = normalize(&self.work_tree);
+
+        match absolute.strip_prefix(&work_tree) {
+            Ok(rel) if rel.as_os_str().is_empty() => Ok(PathBuf::from(".")),
+            Ok(rel) => Ok(rel.to_path_buf()),
+            Err(_) => Err(RitError::InvalidInput(format!(
+                "'{}' is outside the repository",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Makes this the repository the ops and utils of the current
+    /// thread work on until the returned guard is dropped.
+    pub fn activate(&self) -> ActiveRepository {
+        let previous = ACTIVE.with(|active| active.borrow_mut().replace(self.clone()));
+        ActiveRepository { previous }
+    }
+
+    /// Stages files, `paths` are relative to the work tree.
+    pub fn add(&self, paths: &[impl AsRef<Path>]) -> RitResult<Vec<(PathBuf, AddOutcome)>> {
+        let _active = self.activate();
+        let paths: Vec<PathBuf> = paths.iter().map(|p| p.as_ref().to_path_buf()).collect();
+        add::add_paths(&paths)
+    }
+
+    /// Records the INDEX as a new commit on top of HEAD and returns its
+    /// hash, `None` if the INDEX matches the tree of HEAD.
+    pub fn commit(
+        &self,
+        message: &str,
+        author: Option<&str>,
+        date: Option<&str>,
+    ) -> RitResult<Option<Vec<u8>>> {
+        let _active = self.activate();
+        commit::create_commit(message, author, date)
+    }
+
+    pub fn status(&self) -> RitResult<Status> {
+        let _active = self.activate();
+        status::get_status()
+    }
+
+    /// The history of HEAD, newest first, as (hash, commit) pairs.
+    /// See `ops::log::read_log` for `max_count` and `paths`.
+    pub fn log(
+        &self,
+        max_count: Option<usize>,
+        paths: &[impl AsRef<Path>],
+    ) -> RitResult<Vec<(Vec<u8>, Commit)>> {
+        let _active = self.activate();
+        let paths: Vec<PathBuf> = paths.iter().map(|p| p.as_ref().to_path_buf()).collect();
+        log::read_log(max_count, &paths)
+    }
+
+    /// The commit HEAD points to, `None` before the first commit.
+    pub fn head(&self) -> RitResult<Option<Vec<u8>>> {
+        let _active = self.activate();
+        Ok(refutils::resolve_head()?)
+    }
+
+    /// Resolves a branch name, (abbreviated) hash or "<rev>~<n>" to a commit hash.
+    pub fn resolve(&self, revision: &str) -> RitResult<Vec<u8>> {
+        let _active = self.activate();
+        let objects_path = ioutils::get_objects_path()?;
+        Ok(refutils::resolve_revision(&objects_path, revision)?)
+    }
+
+    pub fn read_object(&self, hash: &[u8]) -> RitResult<Object> {
+        let _active = self.activate();
+        let objects_path = ioutils::get_objects_path()?;
+        Ok(ioutils::read_object(&objects_path, &hex::encode(hash))?)
+    }
+}
+
+/// The rit dir of the active repository, ".rit" in the
+/// current dir if none is active.
+pub fn rit_dir() -> PathBuf {
+    ACTIVE.with(|active| match active.borrow().as_ref() {
+        Some(repo) => repo.rit_dir.clone(),
+        None => PathBuf::from(RIT_DIR_NAME),
+    })
+}
+
+/// Where a "./<path>" of INDEX and trees is in the work tree of the
+/// active repository, relative to the current dir if none is active.
+pub fn work_tree_path(path: impl AsRef<Path>) -> PathBuf {
+    ACTIVE.with(|active| match active.borrow().as_ref() {
+        Some(repo) => repo.work_tree.join(path),
+        None => path.as_ref().to_path_buf(),
+    })
+}
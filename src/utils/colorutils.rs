@@ -0,0 +1,46 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+    sync::OnceLock,
+};
+
+use crate::utils::configutils;
+
+pub const RED: &str = "1;31";
+pub const GREEN: &str = "1;32";
+pub const YELLOW: &str = "1;33";
+
+static USE_COLOR: OnceLock<bool> = OnceLock::new();
+
+/// Colour output is controlled by `color.ui`:
+/// - "auto"/"true" (default): colour when stdout is a terminal
+///   and NO_COLOR is not set
+/// - "always": colour
+/// - "never"/"false": no colour
+fn use_color() -> bool {
+    *USE_COLOR.get_or_init(|| {
+        let auto =
+            || io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty());
+
+        match configutils::get_config_value("color.ui")
+            .ok()
+            .flatten()
+            .map(|v| v.to_lowercase())
+            .as_deref()
+        {
+            Some("never") => false,
+            Some("always") => true,
+            Some("auto") | None => auto(),
+            Some(other) => configutils::parse_bool(other).unwrap_or(true) && auto(),
+        }
+    })
+}
+
+/// Wraps `text` in the ANSI escape codes of `color`
+/// unless colour output is disabled.
+pub fn paint(text: &str, color: &str) -> String {
+    match use_color() {
+        true => format!("\u{1b}[{}m{}\u{1b}[0m", color, text),
+        false => text.to_string(),
+    }
+}
@@ -0,0 +1,309 @@
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use crate::models::configmodels::ConfigLevel;
+use crate::repository;
+
+/// - system: /etc/ritconfig (or $RIT_CONFIG_SYSTEM)
+/// - global: ~/.ritconfig (or $RIT_CONFIG_GLOBAL)
+/// - local: .rit/config
+pub fn get_config_path(level: ConfigLevel) -> Option<PathBuf> {
+    match level {
+        ConfigLevel::System => Some(
+            env::var_os("RIT_CONFIG_SYSTEM")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("/etc/ritconfig")),
+        ),
+        ConfigLevel::Global => env::var_os("RIT_CONFIG_GLOBAL")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".ritconfig"))),
+        ConfigLevel::Local => Some(repository::rit_dir().join("config")),
+    }
+}
+
+/// "[section]" -> "section", "[section "sub"]" -> "section.sub"
+fn parse_section_header(line: &str) -> Option<String> {
+    let header = line.strip_prefix('[')?.strip_suffix(']')?;
+    Some(match header.split_once(char::is_whitespace) {
+        Some((name, sub)) => format!(
+            "{}.{}",
+            name.trim().to_lowercase(),
+            sub.trim().trim_matches('"')
+        ),
+        None => header.trim().to_lowercase(),
+    })
+}
+
+/// "name = value" -> ("name", "value"), a key
+/// without a value is a boolean true.
+fn parse_key_value(line: &str) -> (String, String) {
+    match line.split_once('=') {
+        Some((k, v)) => (k.trim().to_lowercase(), unquote(v.trim())),
+        None => (line.trim().to_lowercase(), "true".to_string()),
+    }
+}
+
+fn is_comment_or_empty(line: &str) -> bool {
+    line.is_empty() || line.starts_with('#') || line.starts_with(';')
+}
+
+/// Parses an INI style config into (key, value) pairs where keys
+/// are "section.name" or "section.subsection.name", e.g.
+///
+/// [user]
+///     name = saeid
+///     email = saeidalz96@gmail.com
+///
+/// Section and key names are case-insensitive and lowercased,
+/// lines starting with '#' or ';' are comments.
+pub fn parse_config(content: &str) -> Vec<(String, String)> {
+    let mut pairs = vec![];
+    let mut section = String::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if is_comment_or_empty(line) {
+            continue;
+        }
+
+        if let Some(s) = parse_section_header(line) {
+            section = s;
+            continue;
+        }
+
+        if section.is_empty() {
+            continue;
+        }
+
+        let (key, value) = parse_key_value(line);
+        pairs.push((format!("{}.{}", section, key), value));
+    }
+
+    pairs
+}
+
+fn unquote(value: &str) -> String {
+    // strip trailing comments outside of quotes
+    let mut out = String::new();
+    let mut in_quotes = false;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some(other) => out.push(other),
+                None => {}
+            },
+            '#' | ';' if !in_quotes => break,
+            _ => out.push(c),
+        }
+    }
+    out.trim_end().to_string()
+}
+
+fn quote(value: &str) -> String {
+    let needs_quotes = value != value.trim()
+        || value.contains(['#', ';', '"', '\\', '\n', '\t'])
+        || value.is_empty();
+    if !needs_quotes {
+        return value.to_string();
+    }
+
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+}
+
+/// Splits "section[.subsection].name" into its
+/// lowercased section key and name.
+fn split_key(key: &str) -> io::Result<(String, String)> {
+    let invalid = || Error::new(ErrorKind::InvalidInput, format!("invalid key: {}", key));
+
+    let (section, name) = key.rsplit_once('.').ok_or_else(invalid)?;
+    let section_name = section.split('.').next().unwrap_or("");
+
+    let is_valid_name = |n: &str| {
+        n.starts_with(|c: char| c.is_ascii_alphabetic())
+            && n.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+    if !is_valid_name(name)
+        || section_name.is_empty()
+        || !section_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(invalid());
+    }
+
+    let section = match section.split_once('.') {
+        Some((s, sub)) => format!("{}.{}", s.to_lowercase(), sub),
+        None => section.to_lowercase(),
+    };
+    Ok((section, name.to_lowercase()))
+}
+
+fn read_config_file(path: &Path) -> io::Result<Vec<(String, String)>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(parse_config(&content)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn read_config_level(level: ConfigLevel) -> io::Result<Vec<(String, String)>> {
+    match get_config_path(level) {
+        Some(path) => read_config_file(&path),
+        None => Ok(vec![]),
+    }
+}
+
+/// All entries of all levels in precedence order,
+/// i.e. later entries override earlier ones.
+pub fn list_config() -> io::Result<Vec<(ConfigLevel, String, String)>> {
+    let mut all = vec![];
+    for level in ConfigLevel::ALL {
+        for (k, v) in read_config_level(level)? {
+            all.push((level, k, v));
+        }
+    }
+    Ok(all)
+}
+
+/// Looks a key up in the system, global and local config,
+/// the most specific level defining it wins.
+pub fn get_config_value(key: &str) -> io::Result<Option<String>> {
+    let key = key.to_lowercase();
+
+    Ok(list_config()?
+        .into_iter()
+        .rfind(|(_, k, _)| k.to_lowercase() == key)
+        .map(|(_, _, v)| v))
+}
+
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" | "" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn get_config_bool(key: &str) -> io::Result<Option<bool>> {
+    match get_config_value(key)? {
+        Some(v) => parse_bool(&v).map(Some).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("bad boolean config value '{}' for '{}'", v, key),
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+fn level_path(level: ConfigLevel) -> io::Result<PathBuf> {
+    get_config_path(level).ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("unable to locate the {} config file", level),
+        )
+    })
+}
+
+/// Sets a key in the config file of the given level, replacing
+/// the last existing occurrence or adding it to its section.
+/// Other lines, comments included, are kept as they are.
+pub fn set_config_value(level: ConfigLevel, key: &str, value: &str) -> io::Result<()> {
+    let (section, name) = split_key(key)?;
+    let path = level_path(level)?;
+
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+
+    // the name is written as given, matching is case-insensitive
+    let given_name = key.rsplit_once('.').map(|(_, n)| n).unwrap_or(&name);
+    let new_line = format!("\t{} = {}", given_name, quote(value));
+
+    let mut current = String::new();
+    let mut key_line = None;
+    let mut section_end = None;
+    for (idx, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if let Some(s) = parse_section_header(trimmed) {
+            current = s;
+            if current == section {
+                section_end = Some(idx);
+            }
+            continue;
+        }
+        if current != section || is_comment_or_empty(trimmed) {
+            continue;
+        }
+        section_end = Some(idx);
+        if parse_key_value(trimmed).0 == name {
+            key_line = Some(idx);
+        }
+    }
+
+    match (key_line, section_end) {
+        (Some(idx), _) => lines[idx] = new_line,
+        (None, Some(idx)) => lines.insert(idx + 1, new_line),
+        (None, None) => {
+            let header = match section.split_once('.') {
+                Some((s, sub)) => format!("[{} \"{}\"]", s, sub),
+                None => format!("[{}]", section),
+            };
+            lines.push(header);
+            lines.push(new_line);
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, lines.join("\n") + "\n")
+}
+
+/// Removes every occurrence of a key from the config file of the
+/// given level. Returns false if the key was not set there.
+pub fn unset_config_value(level: ConfigLevel, key: &str) -> io::Result<bool> {
+    let (section, name) = split_key(key)?;
+    let path = level_path(level)?;
+
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+
+    let mut current = String::new();
+    let mut removed = false;
+    let mut lines = vec![];
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if let Some(s) = parse_section_header(trimmed) {
+            current = s;
+        } else if current == section
+            && !is_comment_or_empty(trimmed)
+            && parse_key_value(trimmed).0 == name
+        {
+            removed = true;
+            continue;
+        }
+        lines.push(line);
+    }
+
+    if removed {
+        fs::write(path, lines.join("\n") + "\n")?;
+    }
+    Ok(removed)
+}
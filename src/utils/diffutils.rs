@@ -0,0 +1,186 @@
+/// One step of an edit script turning `a` into `b`,
+/// holding the line indices in `a` and/or `b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOp {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// A group of changes with surrounding context lines.
+/// Starts are 0-based line indices.
+#[derive(Debug)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub ops: Vec<DiffOp>,
+}
+
+/// Splits content into lines, each keeping its "\n".
+pub fn split_lines(content: &[u8]) -> Vec<&[u8]> {
+    content.split_inclusive(|c| *c == b'\n').collect()
+}
+
+/// Content with a null byte in its first 8000 bytes is treated as binary.
+pub fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(8000).any(|c| *c == 0)
+}
+
+/// Shortest edit script between `a` and `b` using Myers' O((N+M)D)
+/// algorithm. The common prefix and suffix are stripped first.
+pub fn myers_diff<T: PartialEq>(a: &[T], b: &[T]) -> Vec<DiffOp> {
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let mut ops: Vec<DiffOp> = (0..prefix).map(|i| DiffOp::Equal(i, i)).collect();
+
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    for op in myers_middle(a_mid, b_mid) {
+        ops.push(match op {
+            DiffOp::Equal(x, y) => DiffOp::Equal(x + prefix, y + prefix),
+            DiffOp::Delete(x) => DiffOp::Delete(x + prefix),
+            DiffOp::Insert(y) => DiffOp::Insert(y + prefix),
+        });
+    }
+
+    let (a_end, b_end) = (a.len() - suffix, b.len() - suffix);
+    ops.extend((0..suffix).map(|i| DiffOp::Equal(a_end + i, b_end + i)));
+
+    ops
+}
+
+fn myers_middle<T: PartialEq>(a: &[T], b: &[T]) -> Vec<DiffOp> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    if max == 0 {
+        return vec![];
+    }
+
+    // v[k + offset] is the furthest x reached on diagonal k = x - y
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    // step d only reads diagonals -d - 1..=d + 1, so only that window
+    // of v is kept per step: O(D^2) instead of O(D * (N + M)) memory
+    let mut trace: Vec<Vec<isize>> = vec![];
+
+    'outer: for d in 0..=max {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'outer;
+            }
+        }
+    }
+
+    // walk the trace back from (n, m) to (0, 0)
+    let mut ops = vec![];
+    let (mut x, mut y) = (n, m);
+    for d in (0..trace.len() as isize).rev() {
+        let v = &trace[d as usize];
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            ops.push(DiffOp::Equal((x - 1) as usize, (y - 1) as usize));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(DiffOp::Insert((y - 1) as usize));
+            } else {
+                ops.push(DiffOp::Delete((x - 1) as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+
+    ops.reverse();
+    ops
+}
+
+/// Groups an edit script into hunks keeping `context` equal lines
+/// around every change. Changes closer than 2 * `context` lines
+/// share a hunk.
+pub fn build_hunks(ops: &[DiffOp], context: usize) -> Vec<Hunk> {
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for i in changes {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let hunk_ops = ops[start..end].to_vec();
+
+            // position of the hunk in both files
+            let (mut old_start, mut new_start) = (0, 0);
+            for op in ops[..start].iter() {
+                match op {
+                    DiffOp::Equal(..) => {
+                        old_start += 1;
+                        new_start += 1;
+                    }
+                    DiffOp::Delete(_) => old_start += 1,
+                    DiffOp::Insert(_) => new_start += 1,
+                }
+            }
+
+            let old_len = hunk_ops
+                .iter()
+                .filter(|op| !matches!(op, DiffOp::Insert(_)))
+                .count();
+            let new_len = hunk_ops
+                .iter()
+                .filter(|op| !matches!(op, DiffOp::Delete(_)))
+                .count();
+
+            Hunk {
+                old_start,
+                old_len,
+                new_start,
+                new_len,
+                ops: hunk_ops,
+            }
+        })
+        .collect()
+}
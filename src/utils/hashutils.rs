@@ -1,8 +1,15 @@
 use sha2::{Digest, Sha256};
 
-pub fn get_hash_from_file(content: &Vec<u8>) -> (String, Vec<u8>) {
+use crate::models::objectmodels::{encode_object, ObjectKind};
+
+pub fn get_hash_from_file(content: &[u8]) -> (String, Vec<u8>) {
     let mut hasher = Sha256::new();
     hasher.update(content);
     let hash_result = hasher.finalize();
     (format!("{:x}", hash_result), hash_result.to_vec())
 }
+
+/// Hash an object of this kind and content is stored under.
+pub fn hash_object(kind: ObjectKind, content: &[u8]) -> (String, Vec<u8>) {
+    get_hash_from_file(&encode_object(kind, content))
+}
@@ -0,0 +1,371 @@
+use std::path::{Path, PathBuf};
+use std::{collections::HashSet, env, fs};
+
+use crate::repository;
+use crate::utils::configutils;
+
+pub const IGNORE_FILE: &str = ".ritignore";
+
+/// One line of a .ritignore file, following gitignore semantics.
+#[derive(Debug)]
+struct IgnorePattern {
+    glob: Vec<char>,
+    negated: bool,
+    // "build/" only matches directories
+    dir_only: bool,
+    // a "/" at the start or in the middle ties the
+    // pattern to the dir of the file it is defined in
+    anchored: bool,
+    // dir of the defining .ritignore relative to root, "" for root
+    base: String,
+}
+
+impl IgnorePattern {
+    fn parse(line: &str, base: &str) -> Option<Self> {
+        let mut line = trim_trailing_spaces(line);
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let mut negated = false;
+        if let Some(rest) = line.strip_prefix('!') {
+            negated = true;
+            line = rest;
+        } else if line.starts_with("\\!") || line.starts_with("\\#") {
+            line = &line[1..];
+        }
+
+        let mut dir_only = false;
+        if let Some(rest) = line.strip_suffix('/') {
+            dir_only = true;
+            line = rest;
+        }
+
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            glob: line.chars().collect(),
+            negated,
+            dir_only,
+            anchored,
+            base: base.to_string(),
+        })
+    }
+
+    /// `rel_path` is relative to root without "./", e.g. "src/main.rs"
+    fn matches(&self, rel_path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        let path = match self.base.is_empty() {
+            true => rel_path,
+            false => match rel_path
+                .strip_prefix(self.base.as_str())
+                .and_then(|p| p.strip_prefix('/'))
+            {
+                Some(p) => p,
+                None => return false,
+            },
+        };
+
+        let text: Vec<char> = match self.anchored {
+            true => path.chars().collect(),
+            false => path.rsplit('/').next().unwrap_or(path).chars().collect(),
+        };
+        glob_match(&self.glob, &text)
+    }
+}
+
+/// Trailing spaces are ignored unless escaped with a backslash.
+fn trim_trailing_spaces(line: &str) -> &str {
+    let trimmed = line.trim_end_matches(['\r', '\n']);
+    let mut end = trimmed.len();
+    while end > 0 && trimmed[..end].ends_with(' ') {
+        if trimmed[..end - 1].ends_with('\\') {
+            break;
+        }
+        end -= 1;
+    }
+    &trimmed[..end]
+}
+
+/// Matches a gitignore glob against a path:
+/// - "*" matches anything but "/"
+/// - "?" matches one character but "/"
+/// - "[a-z]", "[!a-z]" match one character of (or not of) a class
+/// - "**/" matches zero or more directories, "/**" everything inside,
+///   any other "**" matches like "*" but also across "/"
+/// - "\x" matches x literally
+pub fn glob_match(glob: &[char], text: &[char]) -> bool {
+    let (mut g, mut t) = (0, 0);
+
+    while g < glob.len() {
+        match glob[g] {
+            '*' if glob.get(g + 1) == Some(&'*') => {
+                let rest = &glob[g + 2..];
+                let at_component_start = g == 0 || glob[g - 1] == '/';
+
+                if at_component_start && rest.first() == Some(&'/') {
+                    // "**/" zero or more whole directories
+                    let rest = &rest[1..];
+                    if glob_match(rest, &text[t..]) {
+                        return true;
+                    }
+                    return (t..text.len())
+                        .filter(|i| text[*i] == '/')
+                        .any(|i| glob_match(rest, &text[i + 1..]));
+                }
+
+                return (t..=text.len()).any(|i| glob_match(rest, &text[i..]));
+            }
+            '*' => {
+                let rest = &glob[g + 1..];
+                let mut i = t;
+                loop {
+                    if glob_match(rest, &text[i..]) {
+                        return true;
+                    }
+                    if i >= text.len() || text[i] == '/' {
+                        return false;
+                    }
+                    i += 1;
+                }
+            }
+            '?' => {
+                if t >= text.len() || text[t] == '/' {
+                    return false;
+                }
+                g += 1;
+                t += 1;
+            }
+            '[' => match match_class(&glob[g..], text.get(t).copied()) {
+                Some((true, len)) => {
+                    g += len;
+                    t += 1;
+                }
+                Some((false, _)) => return false,
+                // no closing "]", match "[" literally
+                None => {
+                    if text.get(t) != Some(&'[') {
+                        return false;
+                    }
+                    g += 1;
+                    t += 1;
+                }
+            },
+            c => {
+                let (c, len) = match (c, glob.get(g + 1)) {
+                    ('\\', Some(escaped)) => (*escaped, 2),
+                    _ => (c, 1),
+                };
+                if text.get(t) != Some(&c) {
+                    return false;
+                }
+                g += len;
+                t += 1;
+            }
+        }
+    }
+
+    t == text.len()
+}
+
+/// Returns whether `c` is in the class at the start of `glob`
+/// and the length of the class, `None` if it is not closed.
+fn match_class(glob: &[char], c: Option<char>) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(glob.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    while i < glob.len() {
+        if glob[i] == ']' && !first {
+            let found = c.is_some_and(|c| c != '/') && matched != negated;
+            return Some((found, i + 1));
+        }
+        first = false;
+
+        let low = glob[i];
+        if glob.get(i + 1) == Some(&'-') && glob.get(i + 2).is_some_and(|h| *h != ']') {
+            let high = glob[i + 2];
+            matched |= c.is_some_and(|c| low <= c && c <= high);
+            i += 3;
+        } else {
+            matched |= c == Some(low);
+            i += 1;
+        }
+    }
+
+    None
+}
+
+/// Ignore rules from every source, lowest precedence first:
+/// - the file set in core.excludesFile
+/// - .rit/info/exclude
+/// - .ritignore files, deeper ones taking precedence
+///
+/// The last matching pattern decides, "!" patterns re-include.
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<IgnorePattern>,
+    loaded_dirs: HashSet<String>,
+}
+
+impl IgnoreRules {
+    /// Loads every source except the .ritignore files of
+    /// subdirs, those are added with `add_dir` while walking.
+    pub fn load() -> Self {
+        let mut rules = Self::default();
+
+        if let Ok(Some(excludes_file)) = configutils::get_config_value("core.excludesFile") {
+            let excludes_path = match (excludes_file.strip_prefix("~/"), env::var_os("HOME")) {
+                (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+                _ => PathBuf::from(&excludes_file),
+            };
+            rules.add_file(&excludes_path, "");
+        }
+
+        rules.add_file(&repository::rit_dir().join("info").join("exclude"), "");
+        rules.add_dir("");
+
+        rules
+    }
+
+    fn add_file(&mut self, path: &Path, base: &str) {
+        if let Ok(content) = fs::read_to_string(path) {
+            self.patterns.extend(
+                content
+                    .lines()
+                    .filter_map(|l| IgnorePattern::parse(l, base)),
+            );
+        }
+    }
+
+    /// Adds the .ritignore of a dir, `dir` is relative to root, "" for root.
+    pub fn add_dir(&mut self, dir: &str) {
+        if !self.loaded_dirs.insert(dir.to_string()) {
+            return;
+        }
+        let path = match dir.is_empty() {
+            true => repository::work_tree_path(IGNORE_FILE),
+            false => repository::work_tree_path(dir).join(IGNORE_FILE),
+        };
+        self.add_file(&path, dir);
+    }
+
+    /// Checks a single path without looking at its parent dirs.
+    pub fn is_ignored(&self, rel_path: &str, is_dir: bool) -> bool {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|p| p.matches(rel_path, is_dir))
+            .is_some_and(|p| !p.negated)
+    }
+
+    /// Checks a path and all of its parent dirs, a file inside an
+    /// ignored dir is ignored and cannot be re-included.
+    pub fn is_path_ignored(&mut self, rel_path: &str, is_dir: bool) -> bool {
+        let components: Vec<&str> = rel_path.split('/').filter(|c| !c.is_empty()).collect();
+        for i in 1..components.len() {
+            let dir = components[..i].join("/");
+            if self.is_ignored(&dir, true) {
+                return true;
+            }
+            self.add_dir(&dir);
+        }
+        self.is_ignored(rel_path, is_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(glob: &str, text: &str) -> bool {
+        let glob: Vec<char> = glob.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        glob_match(&glob, &text)
+    }
+
+    fn ignores(line: &str, base: &str, rel_path: &str, is_dir: bool) -> bool {
+        IgnorePattern::parse(line, base).is_some_and(|p| p.matches(rel_path, is_dir))
+    }
+
+    #[test]
+    fn star_and_question_mark_stay_in_a_component() {
+        assert!(matches("*.log", "debug.log"));
+        assert!(matches("*", ""));
+        assert!(!matches("*.log", "logs/debug.log"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "a/c"));
+        assert!(!matches("a?c", "ac"));
+    }
+
+    #[test]
+    fn double_star() {
+        assert!(matches("**/foo", "foo"));
+        assert!(matches("**/foo", "a/b/foo"));
+        assert!(!matches("**/foo", "a/xfoo"));
+        assert!(matches("foo/**", "foo/a/b"));
+        assert!(!matches("foo/**", "foo"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(!matches("a/**/b", "ab"));
+        assert!(matches("a**b", "a/x/b"));
+    }
+
+    #[test]
+    fn character_classes() {
+        assert!(matches("[a-c]x", "bx"));
+        assert!(!matches("[a-c]x", "dx"));
+        assert!(matches("[!a-c]x", "dx"));
+        assert!(matches("[^a-c]x", "dx"));
+        assert!(!matches("[!a-c]x", "ax"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[a-]", "-"));
+        assert!(!matches("[!a]", "/"));
+        // an unclosed class is a literal "["
+        assert!(matches("[ab", "[ab"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+        assert!(matches("\\[a]", "[a]"));
+    }
+
+    #[test]
+    fn anchoring() {
+        // no "/" matches the name at any depth
+        assert!(ignores("target", "", "target", true));
+        assert!(ignores("target", "", "a/b/target", true));
+        // a leading or middle "/" ties it to the dir of the .ritignore
+        assert!(ignores("/target", "", "target", true));
+        assert!(!ignores("/target", "", "a/target", true));
+        assert!(ignores("doc/*.txt", "", "doc/a.txt", false));
+        assert!(!ignores("doc/*.txt", "", "src/doc/a.txt", false));
+        // patterns of nested .ritignore files are relative to their dir
+        assert!(ignores("/out", "sub", "sub/out", true));
+        assert!(!ignores("/out", "sub", "out", true));
+        assert!(!ignores("/out", "sub", "subx/out", true));
+    }
+
+    #[test]
+    fn dir_only_and_negation() {
+        assert!(ignores("build/", "", "build", true));
+        assert!(!ignores("build/", "", "build", false));
+        assert!(IgnorePattern::parse("!keep.log", "").is_some_and(|p| p.negated));
+        assert!(IgnorePattern::parse("\\!keep.log", "").is_some_and(|p| !p.negated));
+        assert!(IgnorePattern::parse("# comment", "").is_none());
+        assert!(ignores("trailing\\ ", "", "trailing ", false));
+    }
+}
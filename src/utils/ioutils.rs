@@ -1,164 +1,477 @@
-use std::io::{self, Read, Write};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read, Write};
 use std::path::PathBuf;
 use std::{fs, path::Path};
-use walkdir::{DirEntry, WalkDir};
+use walkdir::WalkDir;
+
+use crate::models::{
+    commitmodels::Commit,
+    errormodels::RitError,
+    indexmodels::{
+        IndexEntry, IndexError, IndexHeader, INDEX_CHECKSUM_LEN, INDEX_SIGNATURE, INDEX_VERSION,
+    },
+    objectmodels::{encode_object, Object, ObjectKind},
+    treemodels::TreeEntry,
+};
+use crate::repository;
+use crate::utils::{
+    hashutils::{get_hash_from_file, hash_object},
+    ignoreutils::IgnoreRules,
+    lockutils::LockFile,
+    packutils,
+};
+
+/// Bounds-checked reads over the bytes of INDEX.
+struct IndexReader<'a> {
+    b: &'a [u8],
+    pos: usize,
+}
 
-use crate::models::indexmodels::{IndexEntry, IndexHeader};
+impl<'a> IndexReader<'a> {
+    fn take(&mut self, n: usize, field: &'static str) -> Result<&'a [u8], IndexError> {
+        let end = self.pos.checked_add(n).filter(|end| *end <= self.b.len());
+        match end {
+            Some(end) => {
+                let bytes = &self.b[self.pos..end];
+                self.pos = end;
+                Ok(bytes)
+            }
+            None => Err(IndexError::Truncated {
+                offset: self.pos,
+                field,
+            }),
+        }
+    }
 
-const IGNORED_PATHS: &[&str] = &[".", ".ritignore"];
+    fn u32(&mut self, field: &'static str) -> Result<u32, IndexError> {
+        let bytes = self.take(4, field)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
 
-fn extract_header(b: &Vec<u8>) -> io::Result<IndexHeader> {
+fn extract_header(r: &mut IndexReader) -> Result<IndexHeader, IndexError> {
     // first 4 bytes are the signature
-    if &b[..4] != b"DIRC" {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "Invalid INDEX file format",
-        ));
+    if r.take(4, "signature")? != INDEX_SIGNATURE {
+        return Err(IndexError::InvalidSignature);
     }
 
-    let version_bytes: [u8; 4] = b[4..8]
-        .try_into()
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-    let version = u32::from_be_bytes(version_bytes);
-
-    let num_entires_byte: [u8; 4] = b[8..12]
-        .try_into()
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-    let num_entries = u32::from_be_bytes(num_entires_byte);
-
-    Ok(IndexHeader::new(num_entries, version, *b"DIRC"))
-}
-
-fn create_index_entry_from_bytes(buffer: &Vec<u8>, offset: usize) -> (IndexEntry, usize) {
-    let ctime_sec = u32::from_be_bytes(buffer[offset..offset + 4].try_into().unwrap());
-    let ctime_nsec = u32::from_be_bytes(buffer[offset + 4..offset + 8].try_into().unwrap());
-    let mtime_sec = u32::from_be_bytes(buffer[offset + 8..offset + 12].try_into().unwrap());
-    let mtime_nsec = u32::from_be_bytes(buffer[offset + 12..offset + 16].try_into().unwrap());
-    let device = u32::from_be_bytes(buffer[offset + 16..offset + 20].try_into().unwrap());
-    let inode = u32::from_be_bytes(buffer[offset + 20..offset + 24].try_into().unwrap());
-    let mode = u32::from_be_bytes(buffer[offset + 24..offset + 28].try_into().unwrap());
-    let size = u32::from_be_bytes(buffer[offset + 28..offset + 32].try_into().unwrap());
-    let sha_hash = buffer[offset + 32..offset + 64].to_vec();
-
-    let mut pos = offset + 64;
-    let file_path_len = u32::from_be_bytes(buffer[pos..pos + 4].try_into().unwrap());
-    pos += 4;
-
-    let file_path = String::from_utf8_lossy(&buffer[pos..pos + file_path_len as usize]).to_string();
-    pos += file_path_len as usize;
-    (
-        IndexEntry {
-            ctime: (ctime_sec, ctime_nsec),
-            mtime: (mtime_sec, mtime_nsec),
-            device,
-            inode,
-            mode,
-            size,
-            sha_hash,
-            file_path_len,
-            file_path,
-        },
-        pos,
-    )
+    let version = r.u32("version")?;
+    if version != 3 && version != INDEX_VERSION {
+        return Err(IndexError::UnsupportedVersion(version));
+    }
+    let num_entries = r.u32("number of entries")?;
+
+    Ok(IndexHeader::new(num_entries, version, INDEX_SIGNATURE))
 }
 
-pub fn read_index() -> io::Result<(IndexHeader, Vec<IndexEntry>)> {
-    let index_path = Path::new("./.rit/INDEX");
-    let mut f = fs::File::open(&index_path)?;
+fn create_index_entry_from_bytes(r: &mut IndexReader) -> Result<IndexEntry, IndexError> {
+    let ctime = (r.u32("ctime")?, r.u32("ctime")?);
+    let mtime = (r.u32("mtime")?, r.u32("mtime")?);
+    let device = r.u32("device")?;
+    let inode = r.u32("inode")?;
+    let mode = r.u32("mode")?;
+    let size = r.u32("size")?;
+    let sha_hash = r.take(32, "hash")?.to_vec();
+
+    let file_path_len = r.u32("file path length")?;
+    let path_offset = r.pos;
+    let file_path = std::str::from_utf8(r.take(file_path_len as usize, "file path")?)
+        .map_err(|_| IndexError::InvalidPath {
+            offset: path_offset,
+        })?
+        .to_string();
+    if file_path.is_empty() {
+        return Err(IndexError::InvalidPath {
+            offset: path_offset,
+        });
+    }
 
-    let mut buffer = vec![];
-    f.read_to_end(&mut buffer)?;
+    // Align to 8-byte boundary
+    // "!7" is an integer mask that clears the last 3 bits
+    // of a number when combined with a bitwise AND. It's
+    // NOTing the 7.
+    // The last 3 bits represent remainders modulo 8.
+    r.take(((r.pos + 7) & !7) - r.pos, "padding")?;
+
+    Ok(IndexEntry {
+        ctime,
+        mtime,
+        device,
+        inode,
+        mode,
+        size,
+        sha_hash,
+        file_path_len,
+        file_path,
+    })
+}
 
-    let header = extract_header(&buffer)?;
+/// Parses the bytes of INDEX. Version 4 ends with a sha256 of the rest
+/// of the file, which has to match. Version 3 has none, and may have
+/// leftovers of a longer INDEX after its entries.
+pub fn parse_index(b: &[u8]) -> Result<(IndexHeader, Vec<IndexEntry>), IndexError> {
+    let mut r = IndexReader { b, pos: 0 };
+    let header = extract_header(&mut r)?;
+
+    if header.version() == INDEX_VERSION {
+        let body_len = b
+            .len()
+            .checked_sub(INDEX_CHECKSUM_LEN)
+            .filter(|len| *len >= r.pos)
+            .ok_or(IndexError::Truncated {
+                offset: b.len(),
+                field: "checksum",
+            })?;
+        if get_hash_from_file(&b[..body_len]).1 != b[body_len..] {
+            return Err(IndexError::ChecksumMismatch);
+        }
+        r.b = &b[..body_len];
+    }
 
     let mut entries = vec![];
-    let mut offset = 12;
-
     for _ in 0..header.num_entries() {
-        let (ie, pos) = create_index_entry_from_bytes(&buffer, offset);
-        entries.push(ie);
-
-        // Align to 8-byte boundary
-        // "!7" is an integer mask that clears the last 3 bits
-        // of a number when combined with a bitwise AND. It's
-        // NOTing the 7.
-        // The last 3 bits represent remainders modulo 8.
-        offset = (pos + 7) & !7;
+        entries.push(create_index_entry_from_bytes(&mut r)?);
+    }
+
+    if header.version() == INDEX_VERSION && r.pos != r.b.len() {
+        return Err(IndexError::TrailingData { offset: r.pos });
     }
 
     Ok((header, entries))
 }
 
+pub fn get_index_path() -> PathBuf {
+    repository::rit_dir().join("INDEX")
+}
+
+/// Parse errors are returned as `ErrorKind::InvalidData`
+/// wrapping the `IndexError`.
+pub fn read_index() -> io::Result<(IndexHeader, Vec<IndexEntry>)> {
+    let buffer = fs::read(get_index_path())?;
+    parse_index(&buffer).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Takes INDEX.lock. Whoever changes INDEX holds it from reading
+/// INDEX until the new one is written with `write_index_locked`,
+/// so concurrent changes cannot overwrite each other.
+pub fn lock_index() -> io::Result<LockFile> {
+    LockFile::acquire(&get_index_path())
+}
+
+/// Always writes the current INDEX_VERSION and the number of
+/// `index_entries`, whatever `index_header` says, followed by
+/// the checksum.
+/// The new INDEX replaces the old one through INDEX.lock.
 pub fn write_index(index_header: IndexHeader, index_entries: Vec<IndexEntry>) -> io::Result<bool> {
-    let index_path = Path::new("./.rit/INDEX");
-    let mut f = if index_path.exists() {
-        fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            // .append(true)
-            .open(index_path)?
-    } else {
-        fs::File::create(index_path)?
-    };
+    write_index_locked(lock_index()?, index_header, index_entries)
+}
 
-    // Check if file is empty, write header
-    // if f.metadata().unwrap().len() == 0 {}
-    f.write(&index_header.signature())?;
-    f.write(&index_header.version().to_be_bytes())?;
-    f.write(&index_header.num_entries().to_be_bytes())?;
+/// `write_index` through a lock taken with `lock_index`.
+pub fn write_index_locked(
+    mut lock: LockFile,
+    index_header: IndexHeader,
+    index_entries: Vec<IndexEntry>,
+) -> io::Result<bool> {
+    let mut b: Vec<u8> = vec![];
 
-    // header is always 3 * 4 bytes
-    let mut pos = 12 as usize;
-    // 9 * 4 + 32  bytes
-    let bytes_until_file_path = 68 as usize;
+    b.extend_from_slice(&index_header.signature());
+    b.extend_from_slice(&INDEX_VERSION.to_be_bytes());
+    b.extend_from_slice(&(index_entries.len() as u32).to_be_bytes());
 
     for ie in index_entries {
-        f.write(&ie.ctime.0.to_be_bytes())?;
-        f.write(&ie.ctime.1.to_be_bytes())?;
-        f.write(&ie.mtime.0.to_be_bytes())?;
-        f.write(&ie.mtime.1.to_be_bytes())?;
-        f.write(&ie.device.to_be_bytes())?;
-        f.write(&ie.inode.to_be_bytes())?;
-        f.write(&ie.mode.to_be_bytes())?;
-        f.write(&ie.size.to_be_bytes())?;
-        f.write(&ie.sha_hash[..])?;
-        f.write(&ie.file_path_len.to_be_bytes())?;
-
-        pos += bytes_until_file_path;
-        pos += f.write(&ie.file_path.as_bytes())?;
-
-        let offset = pos % 8;
+        b.extend_from_slice(&ie.ctime.0.to_be_bytes());
+        b.extend_from_slice(&ie.ctime.1.to_be_bytes());
+        b.extend_from_slice(&ie.mtime.0.to_be_bytes());
+        b.extend_from_slice(&ie.mtime.1.to_be_bytes());
+        b.extend_from_slice(&ie.device.to_be_bytes());
+        b.extend_from_slice(&ie.inode.to_be_bytes());
+        b.extend_from_slice(&ie.mode.to_be_bytes());
+        b.extend_from_slice(&ie.size.to_be_bytes());
+        b.extend_from_slice(&ie.sha_hash[..]);
+        b.extend_from_slice(&ie.file_path_len.to_be_bytes());
+        b.extend_from_slice(ie.file_path.as_bytes());
+
+        let offset = b.len() % 8;
         if offset > 0 {
-            let padding = 8 - offset;
-            f.write_all(&vec![0; padding])?;
-            pos += padding;
+            b.resize(b.len() + 8 - offset, 0);
         }
     }
 
+    let (_, checksum) = get_hash_from_file(&b);
+    b.extend_from_slice(&checksum);
+
+    lock.write_all(&b)?;
+    lock.commit()?;
+
     Ok(true)
 }
 
-pub fn save_file_hash(
-    file_hash: &String,
-    objects_path: &PathBuf,
-    content: &Vec<u8>,
-) -> io::Result<()> {
+pub fn save_file_hash(file_hash: &str, objects_path: &Path, content: &[u8]) -> io::Result<()> {
     let folder_name = &file_hash[..3];
     let file_name = &file_hash[3..];
 
     let path_name = Path::join(objects_path, folder_name);
-    fs::create_dir(&path_name)?;
 
+    // objects are content addressed, an existing one is identical
     let final_path = Path::join(&path_name, file_name);
+    if final_path.exists() || packutils::packed_object_exists(objects_path, file_hash)? {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("object {} already exists", file_hash),
+        ));
+    }
 
-    fs::write(final_path, content)
+    fs::create_dir_all(&path_name)?;
+    fs::write(final_path, compress(content)?)
+}
+
+pub fn compress(content: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content)?;
+    encoder.finish()
+}
+
+pub fn decompress(b: &[u8]) -> io::Result<Vec<u8>> {
+    let mut content = vec![];
+    ZlibDecoder::new(b).read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// Objects written before the envelope and compression existed are
+/// stored as is, under the hash of their raw bytes. Nothing else is
+/// named after the hash of its stored bytes.
+pub fn is_legacy_object(stored: &[u8], file_hash: &str) -> bool {
+    get_hash_from_file(stored).0 == file_hash
+}
+
+/// Wraps content into its object envelope, then stores it under
+/// the hash of the envelope. Storing an existing object is a no-op.
+pub fn write_object(
+    objects_path: &Path,
+    kind: ObjectKind,
+    content: &[u8],
+) -> io::Result<(String, Vec<u8>)> {
+    let object = encode_object(kind, content);
+    let (file_hash, hash_vec) = get_hash_from_file(&object);
+
+    match save_file_hash(&file_hash, objects_path, &object) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => Err(e),
+        _ => Ok((file_hash, hash_vec)),
+    }
+}
+
+/// The bytes of a loose object as they are on disk,
+/// `None` if there is no loose object with this hash.
+pub fn read_loose_object(objects_path: &Path, file_hash: &str) -> io::Result<Option<Vec<u8>>> {
+    let folder_name = &file_hash[..3];
+    let file_name = &file_hash[3..];
+
+    match fs::read(Path::join(objects_path, folder_name).join(file_name)) {
+        Ok(stored) => Ok(Some(stored)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// The stored bytes of an object, envelope included.
+/// Loose objects are looked up first, then the packs.
+pub fn read_raw_object(objects_path: &Path, file_hash: &str) -> io::Result<Vec<u8>> {
+    if file_hash.len() != 64 || !file_hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(
+            RitError::InvalidInput(format!("'{}' is not an object hash", file_hash)).into(),
+        );
+    }
+    let folder_name = &file_hash[..3];
+    let file_name = &file_hash[3..];
+
+    match fs::read(Path::join(objects_path, folder_name).join(file_name)) {
+        Ok(stored) => decompress(&stored).map_err(|e| {
+            let reason = match is_legacy_object(&stored, file_hash) {
+                true => "stored by an older rit, run `rit migrate`".to_string(),
+                false => e.to_string(),
+            };
+            RitError::CorruptObject {
+                hash: file_hash.to_string(),
+                reason,
+            }
+            .into()
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            packutils::read_packed_object(objects_path, file_hash)?
+                .ok_or_else(|| RitError::MissingObject(file_hash.to_string()).into())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub fn read_object(objects_path: &Path, file_hash: &str) -> io::Result<Object> {
+    Object::from_bytes(&read_raw_object(objects_path, file_hash)?).map_err(|e| {
+        RitError::CorruptObject {
+            hash: file_hash.to_string(),
+            reason: e.to_string(),
+        }
+        .into()
+    })
+}
+
+fn unexpected_kind(file_hash: &str, expected: ObjectKind, found: ObjectKind) -> io::Error {
+    RitError::CorruptObject {
+        hash: file_hash.to_string(),
+        reason: format!("is a {}, not a {}", found, expected),
+    }
+    .into()
+}
+
+pub fn read_blob(objects_path: &Path, blob_hash: &[u8]) -> io::Result<Vec<u8>> {
+    let file_hash = hex::encode(blob_hash);
+    match read_object(objects_path, &file_hash)? {
+        Object::Blob(content) => Ok(content),
+        other => Err(unexpected_kind(&file_hash, ObjectKind::Blob, other.kind())),
+    }
+}
+
+pub fn object_exists(objects_path: &Path, file_hash: &str) -> bool {
+    let folder_name = &file_hash[..3];
+    let file_name = &file_hash[3..];
+
+    Path::join(objects_path, folder_name)
+        .join(file_name)
+        .is_file()
+        || packutils::packed_object_exists(objects_path, file_hash).unwrap_or(false)
+}
+
+/// Hashes and paths of all loose objects, sorted by hash.
+pub fn list_loose_objects(objects_path: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut objects = vec![];
+    for entry in WalkDir::new(objects_path).min_depth(2).max_depth(2) {
+        let entry = entry.map_err(io::Error::other)?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let folder_name = match entry.path().parent().and_then(|p| p.file_name()) {
+            Some(f) => f.to_string_lossy(),
+            None => continue,
+        };
+        let file_hash = format!("{}{}", folder_name, entry.file_name().to_string_lossy());
+        if file_hash.len() == 64 && file_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            objects.push((file_hash, entry.into_path()));
+        }
+    }
+    objects.sort();
+
+    Ok(objects)
+}
+
+/// Full hashes of all loose and packed objects whose hash starts with
+/// `prefix`. The prefix must cover at least the 3 characters of the folder name.
+pub fn find_objects_by_prefix(objects_path: &Path, prefix: &str) -> io::Result<Vec<String>> {
+    if prefix.len() < 3 {
+        return Ok(vec![]);
+    }
+
+    let mut matches: Vec<String> = packutils::list_packed_objects(objects_path)?
+        .into_iter()
+        .filter(|h| h.starts_with(prefix))
+        .collect();
+
+    let folder_name = &prefix[..3];
+    let entries = match fs::read_dir(objects_path.join(folder_name)) {
+        Ok(e) => e,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(matches),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let file_name = entry?.file_name().to_string_lossy().into_owned();
+        if file_name.starts_with(&prefix[3..]) {
+            matches.push(format!("{}{}", folder_name, file_name));
+        }
+    }
+    matches.sort();
+    matches.dedup();
+
+    Ok(matches)
+}
+
+pub fn read_commit(objects_path: &Path, commit_hash: &[u8]) -> io::Result<Commit> {
+    let file_hash = hex::encode(commit_hash);
+    match read_object(objects_path, &file_hash)? {
+        Object::Commit(commit) => Ok(commit),
+        other => Err(unexpected_kind(
+            &file_hash,
+            ObjectKind::Commit,
+            other.kind(),
+        )),
+    }
+}
+
+pub fn read_tree(objects_path: &Path, tree_hash: &[u8]) -> io::Result<Vec<TreeEntry>> {
+    let file_hash = hex::encode(tree_hash);
+    match read_object(objects_path, &file_hash)? {
+        Object::Tree(entries) => Ok(entries),
+        other => Err(unexpected_kind(&file_hash, ObjectKind::Tree, other.kind())),
+    }
+}
+
+/// Reads a tree and all of its subtrees into a flat list of file
+/// entries whose paths are relative to root, e.g. "./dir/file".
+pub fn read_tree_recursive(objects_path: &Path, tree_hash: &[u8]) -> io::Result<Vec<TreeEntry>> {
+    let mut entries = vec![];
+    flatten_tree(objects_path, tree_hash, ".", &mut entries)?;
+    Ok(entries)
+}
+
+fn flatten_tree(
+    objects_path: &Path,
+    tree_hash: &[u8],
+    prefix: &str,
+    entries: &mut Vec<TreeEntry>,
+) -> io::Result<()> {
+    for te in read_tree(objects_path, tree_hash)? {
+        let is_tree = te.is_tree();
+        // flat trees of older commits already hold the full path
+        let file_path = match te.file_path.starts_with("./") {
+            true => te.file_path,
+            false => format!("{}/{}", prefix, te.file_path),
+        };
+
+        if is_tree {
+            flatten_tree(objects_path, &te.sha_hash, &file_path, entries)?;
+        } else {
+            entries.push(TreeEntry {
+                mode: te.mode,
+                file_path,
+                sha_hash: te.sha_hash,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Path -> entry of the tree of a commit, empty for no commit.
+pub fn get_tree_map(
+    objects_path: &Path,
+    commit_hash: Option<&Vec<u8>>,
+) -> io::Result<HashMap<String, TreeEntry>> {
+    let commit_hash = match commit_hash {
+        Some(h) => h,
+        None => return Ok(HashMap::new()),
+    };
+
+    let commit = read_commit(objects_path, commit_hash)?;
+    Ok(read_tree_recursive(objects_path, &commit.tree_hash)?
+        .into_iter()
+        .map(|te| (te.file_path.clone(), te))
+        .collect())
 }
 
 /// Possible Errors:
 /// - Path points to a directory.
 /// - The file doesn’t exist.
 /// - The user lacks permissions to remove the file.
-pub fn delete_file_hash(objects_path: &PathBuf, file_hash: &String) -> io::Result<()> {
+pub fn delete_file_hash(objects_path: &Path, file_hash: &str) -> io::Result<()> {
     let folder_name = &file_hash[..3];
     let file_name = &file_hash[3..];
 
@@ -169,89 +482,107 @@ pub fn delete_file_hash(objects_path: &PathBuf, file_hash: &String) -> io::Resul
 }
 
 pub fn get_objects_path() -> Result<PathBuf, io::Error> {
-    let rit_dir = Path::new(".rit");
-    let objects_path = rit_dir.join("objects");
+    let objects_path = repository::rit_dir().join("objects");
 
     if !objects_path.exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            "No .rit directory found",
-        ));
+        return Err(RitError::NotARepository(repository::rit_dir()).into());
     }
 
     Ok(objects_path)
 }
 
-fn is_hidden(entry: &DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s.starts_with("."))
-        .unwrap_or(false)
+/// Blob hash of a working tree file, `None` if it does not exist,
+/// also when a dir took its place or a file the place of its dir.
+pub fn get_worktree_hash(file_path: &str) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(repository::work_tree_path(file_path)) {
+        Ok(content) => Ok(Some(hash_object(ObjectKind::Blob, &content).1)),
+        Err(e)
+            if matches!(
+                e.kind(),
+                ErrorKind::NotFound | ErrorKind::IsADirectory | ErrorKind::NotADirectory
+            ) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
 }
 
-fn should_ignore(e: &DirEntry, ignore_list: &Vec<PathBuf>) -> bool {
-    if let Some(name) = e.file_name().to_str() {
-        ignore_list
-            .iter()
-            .any(|term| name.contains(term.to_str().unwrap_or("")))
-    } else {
-        false
+/// Removes a file and the dirs that became empty because of it.
+pub fn remove_worktree_file(file_path: &str) -> io::Result<()> {
+    match fs::remove_file(repository::work_tree_path(file_path)) {
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
     }
-}
 
-fn should_ignore_or_hidden(entry: &DirEntry, ignore_list: &Vec<PathBuf>) -> bool {
-    if let Some(path_str) = entry.path().to_str() {
-        if IGNORED_PATHS.contains(&path_str) {
-            return false;
+    let mut dir = Path::new(file_path).parent();
+    while let Some(d) = dir {
+        if d == Path::new(".")
+            || d.as_os_str().is_empty()
+            || fs::remove_dir(repository::work_tree_path(d)).is_err()
+        {
+            break;
         }
+        dir = d.parent();
     }
-    should_ignore(entry, ignore_list) || is_hidden(entry)
+
+    Ok(())
 }
 
-fn get_ignore_list() -> Vec<PathBuf> {
-    let mut ignore_list: Vec<PathBuf> = vec![];
+fn walk_dir(
+    dir: &Path,
+    rel_dir: &str,
+    rit_dir: &Path,
+    rules: &mut IgnoreRules,
+    paths: &mut Vec<PathBuf>,
+) {
+    if !rel_dir.is_empty() {
+        rules.add_dir(rel_dir);
+    }
 
-    // TODO: take into account that .ritignore might not exist
-    // Add entries from .ritignore file
-    match std::fs::read_to_string(".ritignore") {
-        Ok(res) => {
-            for line in res.lines() {
-                ignore_list.push(PathBuf::from(line));
-            }
+    // skip the non-permitted dirs
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+
+    let mut entries: Vec<fs::DirEntry> = entries.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        let rel_path = match rel_dir.is_empty() {
+            true => name.clone(),
+            false => format!("{}/{}", rel_dir, name),
+        };
+
+        // the rit dir may be given with --rit-dir under any name,
+        // other dotfiles are up to the ignore rules
+        if entry.path() == rit_dir || rules.is_ignored(&rel_path, is_dir) {
+            continue;
         }
-        Err(e) => eprintln!("{}", e),
-    }
 
-    ignore_list
+        if is_dir {
+            walk_dir(&entry.path(), &rel_path, rit_dir, rules, paths);
+        } else {
+            paths.push(Path::new(".").join(&rel_path));
+        }
+    }
 }
 
+/// All files of the working tree that are not ignored, as "./<path>".
 pub fn get_all_paths() -> Vec<PathBuf> {
-    let ignore_list = get_ignore_list();
-
-    let root_dir = Path::new(".");
+    let mut rules = IgnoreRules::load();
     let mut paths = vec![];
 
-    // search for files in "."
-    if let Ok(entries) = std::fs::read_dir(root_dir) {
-        for e in entries.filter_map(|e| e.ok()) {
-            if e.path().is_file() {
-                paths.push(e.path());
-            }
-        }
-    }
+    let work_tree = repository::work_tree_path(".");
+    let rit_dir = repository::rit_dir();
+    let rit_dir = rit_dir.canonicalize().unwrap_or(rit_dir);
+    let work_tree = work_tree.canonicalize().unwrap_or(work_tree);
 
-    // search all subdirs
-    for entry in WalkDir::new(root_dir)
-        .into_iter()
-        .filter_entry(|e| !should_ignore_or_hidden(e, &ignore_list))
-        // skip the non-permitted dirs
-        .filter_map(|e| e.ok())
-    {
-        if !entry.path().is_dir() {
-            paths.push(entry.into_path());
-        }
-    }
+    walk_dir(&work_tree, "", &rit_dir, &mut rules, &mut paths);
 
     paths
 }
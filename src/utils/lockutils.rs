@@ -0,0 +1,112 @@
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::models::errormodels::RitError;
+use crate::utils::configutils;
+
+/// Exclusive lock on a file of the repository, e.g. INDEX or a ref.
+/// New content goes to "<file>.lock", which is renamed over the file
+/// on `commit`, so readers see either the old or the new content and
+/// a crash never leaves a half written file behind. The lock file
+/// itself is the lock: only one process can create it.
+///
+/// Dropping a `LockFile` without committing discards the new content.
+pub struct LockFile {
+    path: PathBuf,
+    lock_path: PathBuf,
+    file: fs::File,
+    committed: bool,
+}
+
+fn lock_path_of(path: &Path) -> PathBuf {
+    let mut lock_path = OsString::from(path.as_os_str());
+    lock_path.push(".lock");
+    PathBuf::from(lock_path)
+}
+
+/// `core.fsync` (default true) controls whether locked writes are
+/// flushed to disk before they replace the original file.
+fn fsync_enabled() -> bool {
+    configutils::get_config_bool("core.fsync")
+        .ok()
+        .flatten()
+        .unwrap_or(true)
+}
+
+impl LockFile {
+    /// Fails with `RitError::LockContention` if another
+    /// process holds the lock.
+    pub fn acquire(path: &Path) -> io::Result<Self> {
+        let lock_path = lock_path_of(path);
+        let file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_path)
+        {
+            Ok(f) => f,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                return Err(RitError::LockContention(lock_path).into())
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            lock_path,
+            file,
+            committed: false,
+        })
+    }
+
+    pub fn write_all(&mut self, content: &[u8]) -> io::Result<()> {
+        self.file.write_all(content)
+    }
+
+    /// Replaces the locked file with what was written and releases the lock.
+    pub fn commit(mut self) -> io::Result<()> {
+        let fsync = fsync_enabled();
+        if fsync {
+            self.file.sync_all()?;
+        }
+
+        fs::rename(&self.lock_path, &self.path)?;
+        self.committed = true;
+
+        if fsync {
+            // the rename itself is only durable once the dir is synced
+            if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                fs::File::open(dir)?.sync_all()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the locked file and releases the lock.
+    pub fn remove(mut self) -> io::Result<()> {
+        fs::remove_file(&self.path)?;
+        fs::remove_file(&self.lock_path)?;
+        self.committed = true;
+
+        Ok(())
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.lock_path);
+        }
+    }
+}
+
+/// Writes `content` to `path` through a lock file.
+pub fn write_locked(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut lock = LockFile::acquire(path)?;
+    lock.write_all(content)?;
+    lock.commit()
+}
@@ -1,3 +1,12 @@
+pub mod colorutils;
+pub mod configutils;
+pub mod diffutils;
 pub mod hashutils;
+pub mod ignoreutils;
 pub mod ioutils;
+pub mod jsonutils;
+pub mod lockutils;
+pub mod packutils;
 pub mod pathutils;
+pub mod refutils;
+pub mod timeutils;
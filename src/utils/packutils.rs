@@ -0,0 +1,620 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::models::{
+    errormodels::RitError,
+    objectmodels::{decode_object, ObjectKind},
+};
+use crate::utils::{hashutils::get_hash_from_file, ioutils};
+
+const PACK_SIGNATURE: &[u8; 4] = b"PACK";
+const INDEX_SIGNATURE: &[u8; 4] = b"RIDX";
+const PACK_VERSION: u32 = 1;
+
+// signature, version and number of objects
+const HEADER_LEN: u64 = 12;
+// number of hashes starting with a byte <= i, for every byte
+const FANOUT_LEN: u64 = 256 * 4;
+
+const ENTRY_FULL: u8 = 0;
+const ENTRY_DELTA: u8 = 1;
+
+const DELTA_COPY: u8 = 1;
+const DELTA_INSERT: u8 = 2;
+// shortest match worth a copy instruction
+const DELTA_BLOCK: usize = 16;
+// how many of the previous blobs are tried as delta bases
+const DELTA_WINDOW: usize = 10;
+// longest chain of deltas a read has to resolve
+const MAX_DELTA_DEPTH: usize = 16;
+
+fn corrupt(path: &Path, msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), msg),
+    )
+}
+
+pub fn get_packs_path(objects_path: &Path) -> PathBuf {
+    objects_path.join("pack")
+}
+
+/// The ".idx" files of all packs, sorted.
+pub fn list_pack_indexes(objects_path: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(get_packs_path(objects_path)) {
+        Ok(e) => e,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    let mut indexes = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "idx") {
+            indexes.push(path);
+        }
+    }
+    indexes.sort();
+
+    Ok(indexes)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_varint(b: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut n: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *b.get(*pos)?;
+        *pos += 1;
+        n |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(n);
+        }
+    }
+    None
+}
+
+/// Delta that rebuilds `target` from `base`:
+/// - <BASE_LEN> <TARGET_LEN> (varints)
+/// - then instructions, either
+///   - DELTA_COPY <OFFSET> <LEN>: copy a range of the base
+///   - DELTA_INSERT <LEN> <BYTES>: insert new bytes
+///
+/// Matches are found by indexing every DELTA_BLOCK bytes of the base.
+pub fn create_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut delta = vec![];
+    write_varint(&mut delta, base.len() as u64);
+    write_varint(&mut delta, target.len() as u64);
+
+    let mut blocks: HashMap<&[u8], usize> = HashMap::new();
+    for start in (0..base.len().saturating_sub(DELTA_BLOCK - 1)).step_by(DELTA_BLOCK) {
+        blocks
+            .entry(&base[start..start + DELTA_BLOCK])
+            .or_insert(start);
+    }
+
+    let flush = |delta: &mut Vec<u8>, pending: &mut Vec<u8>| {
+        if !pending.is_empty() {
+            delta.push(DELTA_INSERT);
+            write_varint(delta, pending.len() as u64);
+            delta.append(pending);
+        }
+    };
+
+    let mut pending: Vec<u8> = vec![];
+    let mut pos = 0;
+    while pos < target.len() {
+        let found = target
+            .get(pos..pos + DELTA_BLOCK)
+            .and_then(|window| blocks.get(window));
+
+        match found {
+            Some(&base_start) => {
+                let len = base[base_start..]
+                    .iter()
+                    .zip(&target[pos..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                flush(&mut delta, &mut pending);
+                delta.push(DELTA_COPY);
+                write_varint(&mut delta, base_start as u64);
+                write_varint(&mut delta, len as u64);
+                pos += len;
+            }
+            None => {
+                pending.push(target[pos]);
+                pos += 1;
+            }
+        }
+    }
+    flush(&mut delta, &mut pending);
+
+    delta
+}
+
+/// Rebuilds the target of a delta made by `create_delta`.
+pub fn apply_delta(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    if read_varint(delta, &mut pos)? != base.len() as u64 {
+        return None;
+    }
+    let target_len = read_varint(delta, &mut pos)? as usize;
+
+    // the header is untrusted, every op adds at most
+    // base.len() bytes or the bytes it carries itself
+    let mut target = Vec::with_capacity(target_len.min(delta.len() + base.len()));
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        match op {
+            DELTA_COPY => {
+                let start = read_varint(delta, &mut pos)? as usize;
+                let len = read_varint(delta, &mut pos)? as usize;
+                if len > target_len - target.len() {
+                    return None;
+                }
+                target.extend_from_slice(base.get(start..start.checked_add(len)?)?);
+            }
+            DELTA_INSERT => {
+                let len = read_varint(delta, &mut pos)? as usize;
+                if len > target_len - target.len() {
+                    return None;
+                }
+                target.extend_from_slice(delta.get(pos..pos.checked_add(len)?)?);
+                pos += len;
+            }
+            _ => return None,
+        }
+    }
+
+    match target.len() == target_len {
+        true => Some(target),
+        false => None,
+    }
+}
+
+fn read_u32_at(f: &mut fs::File, offset: u64) -> io::Result<u32> {
+    let mut b = [0; 4];
+    f.seek(SeekFrom::Start(offset))?;
+    f.read_exact(&mut b)?;
+    Ok(u32::from_be_bytes(b))
+}
+
+/// Looks a hash up in a pack index and returns the offset
+/// of its entry in the pack. The fanout table narrows the
+/// search to hashes sharing the first byte, the rest is a
+/// binary search, so only a handful of reads are needed.
+fn find_in_index(idx_path: &Path, hash: &[u8]) -> io::Result<Option<u64>> {
+    if hash.is_empty() {
+        return Ok(None);
+    }
+
+    let mut f = fs::File::open(idx_path)?;
+    let mut header = [0; HEADER_LEN as usize];
+    f.read_exact(&mut header)?;
+    if &header[..4] != INDEX_SIGNATURE {
+        return Err(corrupt(idx_path, "not a pack index"));
+    }
+    let count = u64::from(u32::from_be_bytes(header[8..12].try_into().unwrap()));
+
+    let first = u64::from(hash[0]);
+    let mut lo = match first {
+        0 => 0,
+        _ => u64::from(read_u32_at(&mut f, HEADER_LEN + (first - 1) * 4)?),
+    };
+    let mut hi = u64::from(read_u32_at(&mut f, HEADER_LEN + first * 4)?);
+    if hi > count || lo > hi {
+        return Err(corrupt(idx_path, "invalid fanout table"));
+    }
+
+    let hashes_start = HEADER_LEN + FANOUT_LEN;
+    let mut candidate = [0; 32];
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        f.seek(SeekFrom::Start(hashes_start + mid * 32))?;
+        f.read_exact(&mut candidate)?;
+
+        match candidate[..].cmp(hash) {
+            std::cmp::Ordering::Equal => {
+                let mut offset = [0; 8];
+                f.seek(SeekFrom::Start(hashes_start + count * 32 + mid * 8))?;
+                f.read_exact(&mut offset)?;
+                return Ok(Some(u64::from_be_bytes(offset)));
+            }
+            std::cmp::Ordering::Less => lo = mid + 1,
+            std::cmp::Ordering::Greater => hi = mid,
+        }
+    }
+
+    Ok(None)
+}
+
+/// All hashes listed in a pack index, sorted.
+pub fn read_index_hashes(idx_path: &Path) -> io::Result<Vec<Vec<u8>>> {
+    let b = fs::read(idx_path)?;
+    if b.len() < (HEADER_LEN + FANOUT_LEN) as usize || &b[..4] != INDEX_SIGNATURE {
+        return Err(corrupt(idx_path, "not a pack index"));
+    }
+    let count = u32::from_be_bytes(b[8..12].try_into().unwrap()) as usize;
+
+    let start = (HEADER_LEN + FANOUT_LEN) as usize;
+    let hashes = b
+        .get(start..start + count * 32)
+        .ok_or_else(|| corrupt(idx_path, "truncated pack index"))?;
+
+    Ok(hashes.chunks(32).map(|h| h.to_vec()).collect())
+}
+
+/// Checks the checksums of a pack and its index, and
+/// that the index belongs to the pack next to it.
+pub fn verify_pack(idx_path: &Path) -> io::Result<()> {
+    let pack_path = pack_path_of(idx_path);
+    let pack = fs::read(&pack_path)?;
+    let index = fs::read(idx_path)?;
+
+    let split_checksum = |b: &[u8], path: &Path| -> io::Result<(Vec<u8>, Vec<u8>)> {
+        match b.len().checked_sub(32) {
+            Some(end) if end >= HEADER_LEN as usize => Ok((b[..end].to_vec(), b[end..].to_vec())),
+            _ => Err(corrupt(path, "file is truncated")),
+        }
+    };
+
+    let (pack_body, pack_checksum) = split_checksum(&pack, &pack_path)?;
+    if get_hash_from_file(&pack_body).1 != pack_checksum {
+        return Err(corrupt(&pack_path, "checksum mismatch"));
+    }
+
+    let (index_body, index_checksum) = split_checksum(&index, idx_path)?;
+    if get_hash_from_file(&index_body).1 != index_checksum {
+        return Err(corrupt(idx_path, "checksum mismatch"));
+    }
+    if !index_body.ends_with(&pack_checksum) {
+        return Err(corrupt(idx_path, "does not belong to its pack"));
+    }
+
+    Ok(())
+}
+
+fn pack_path_of(idx_path: &Path) -> PathBuf {
+    idx_path.with_extension("pack")
+}
+
+/// Reads the entry at `offset` of a pack. Deltas are resolved
+/// through their base, wherever it is. `depth` is the number of
+/// deltas already being resolved for the object that was asked for.
+fn read_pack_entry(
+    objects_path: &Path,
+    pack_path: &Path,
+    offset: u64,
+    depth: usize,
+) -> io::Result<Vec<u8>> {
+    let mut f = fs::File::open(pack_path)?;
+    f.seek(SeekFrom::Start(offset))?;
+
+    let mut entry_type = [0; 1];
+    f.read_exact(&mut entry_type)?;
+    let base_hash = match entry_type[0] {
+        ENTRY_FULL => None,
+        ENTRY_DELTA => {
+            let mut base_hash = [0; 32];
+            f.read_exact(&mut base_hash)?;
+            Some(base_hash)
+        }
+        _ => return Err(corrupt(pack_path, "unknown entry type")),
+    };
+
+    let mut len = [0; 8];
+    f.read_exact(&mut len)?;
+    let len = u64::from_be_bytes(len);
+    let mut data = vec![];
+    f.take(len).read_to_end(&mut data)?;
+    if data.len() as u64 != len {
+        return Err(corrupt(pack_path, "truncated entry"));
+    }
+    let data = ioutils::decompress(&data)?;
+
+    match base_hash {
+        None => Ok(data),
+        Some(base_hash) => {
+            let base = read_delta_base(objects_path, &hex::encode(base_hash), depth + 1)?;
+            apply_delta(&base, &data).ok_or_else(|| corrupt(pack_path, "invalid delta"))
+        }
+    }
+}
+
+/// A corrupt pack can hold a chain of deltas that never ends,
+/// e.g. two deltas being each other's base, so the chain is cut
+/// off at the longest one `write_pack` creates.
+fn read_delta_base(objects_path: &Path, file_hash: &str, depth: usize) -> io::Result<Vec<u8>> {
+    if depth > MAX_DELTA_DEPTH {
+        return Err(RitError::CorruptObject {
+            hash: file_hash.to_string(),
+            reason: format!("delta chain is longer than {}", MAX_DELTA_DEPTH),
+        }
+        .into());
+    }
+
+    match read_packed_object_at_depth(objects_path, file_hash, depth)? {
+        Some(base) => Ok(base),
+        None => ioutils::read_raw_object(objects_path, file_hash),
+    }
+}
+
+fn read_packed_object_at_depth(
+    objects_path: &Path,
+    file_hash: &str,
+    depth: usize,
+) -> io::Result<Option<Vec<u8>>> {
+    let hash = hex::decode(file_hash).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+
+    for idx_path in list_pack_indexes(objects_path)? {
+        if let Some(offset) = find_in_index(&idx_path, &hash)? {
+            return read_pack_entry(objects_path, &pack_path_of(&idx_path), offset, depth)
+                .map(Some);
+        }
+    }
+
+    Ok(None)
+}
+
+/// The stored bytes of a packed object, `None` if no pack has it.
+pub fn read_packed_object(objects_path: &Path, file_hash: &str) -> io::Result<Option<Vec<u8>>> {
+    read_packed_object_at_depth(objects_path, file_hash, 0)
+}
+
+pub fn packed_object_exists(objects_path: &Path, file_hash: &str) -> io::Result<bool> {
+    let hash = match hex::decode(file_hash) {
+        Ok(h) => h,
+        Err(_) => return Ok(false),
+    };
+
+    for idx_path in list_pack_indexes(objects_path)? {
+        if find_in_index(&idx_path, &hash)?.is_some() {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Hex hashes of all packed objects, sorted and deduplicated.
+pub fn list_packed_objects(objects_path: &Path) -> io::Result<Vec<String>> {
+    let mut objects = vec![];
+    for idx_path in list_pack_indexes(objects_path)? {
+        objects.extend(read_index_hashes(&idx_path)?.into_iter().map(hex::encode));
+    }
+    objects.sort();
+    objects.dedup();
+
+    Ok(objects)
+}
+
+pub struct PackStats {
+    pub pack_path: PathBuf,
+    pub objects: usize,
+    pub deltas: usize,
+    pub size: u64,
+}
+
+/// Picks a delta base for every blob: blobs are sorted by size and
+/// each one is tried against the previous DELTA_WINDOW blobs. A delta
+/// is only kept when it is less than half of the object.
+/// Returns hash -> (base hash, delta).
+fn find_deltas(objects: &[(Vec<u8>, Vec<u8>)]) -> HashMap<Vec<u8>, (Vec<u8>, Vec<u8>)> {
+    let mut blobs: Vec<&(Vec<u8>, Vec<u8>)> = objects
+        .iter()
+        .filter(|(_, raw)| matches!(decode_object(raw), Ok((ObjectKind::Blob, _))))
+        .collect();
+    blobs.sort_by_key(|(_, raw)| raw.len());
+
+    let mut deltas: HashMap<Vec<u8>, (Vec<u8>, Vec<u8>)> = HashMap::new();
+    let mut depths: HashMap<&[u8], usize> = HashMap::new();
+    for (i, (hash, raw)) in blobs.iter().enumerate() {
+        let mut best: Option<(&Vec<u8>, Vec<u8>)> = None;
+        for (base_hash, base_raw) in blobs[i.saturating_sub(DELTA_WINDOW)..i].iter() {
+            let depth = depths.get(base_hash.as_slice()).copied().unwrap_or(0);
+            if depth >= MAX_DELTA_DEPTH {
+                continue;
+            }
+            let delta = create_delta(base_raw, raw);
+            if delta.len() < raw.len() / 2
+                && best.as_ref().is_none_or(|(_, d)| delta.len() < d.len())
+            {
+                best = Some((base_hash, delta));
+            }
+        }
+
+        if let Some((base_hash, delta)) = best {
+            let depth = depths.get(base_hash.as_slice()).copied().unwrap_or(0) + 1;
+            depths.insert(hash.as_slice(), depth);
+            deltas.insert(hash.clone(), (base_hash.clone(), delta));
+        }
+    }
+
+    deltas
+}
+
+/// Pack file consists of:
+/// - "PACK", <VERSION> (4 bytes), <NUM_OBJECTS> (4 bytes)
+/// - per object, in hash order:
+///   - entry type (1 byte): full object or delta
+///   - <BASE_HASH> (32 bytes, deltas only)
+///   - <LEN> (8 bytes) followed by the compressed object or delta
+/// - sha256 of all the above (32 bytes)
+///
+/// Index file consists of:
+/// - "RIDX", <VERSION> (4 bytes), <NUM_OBJECTS> (4 bytes)
+/// - fanout table: 256 * 4 bytes
+/// - sorted hashes: <NUM_OBJECTS> * 32 bytes
+/// - offsets of the entries in the pack: <NUM_OBJECTS> * 8 bytes
+/// - pack checksum (32 bytes), then sha256 of all the above (32 bytes)
+///
+/// `objects` are (hash, stored bytes) pairs. Both files are written
+/// under a temporary name first, the index last, so readers never
+/// find an index pointing into an incomplete pack.
+pub fn write_pack(
+    objects_path: &Path,
+    mut objects: Vec<(Vec<u8>, Vec<u8>)>,
+) -> io::Result<PackStats> {
+    objects.sort();
+    objects.dedup_by(|a, b| a.0 == b.0);
+    let deltas = find_deltas(&objects);
+
+    let mut pack = vec![];
+    pack.extend_from_slice(PACK_SIGNATURE);
+    pack.extend_from_slice(&PACK_VERSION.to_be_bytes());
+    pack.extend_from_slice(&(objects.len() as u32).to_be_bytes());
+
+    let mut offsets = vec![];
+    for (hash, raw) in objects.iter() {
+        offsets.push(pack.len() as u64);
+        let data = match deltas.get(hash) {
+            Some((base_hash, delta)) => {
+                pack.push(ENTRY_DELTA);
+                pack.extend_from_slice(base_hash);
+                ioutils::compress(delta)?
+            }
+            None => {
+                pack.push(ENTRY_FULL);
+                ioutils::compress(raw)?
+            }
+        };
+        pack.extend_from_slice(&(data.len() as u64).to_be_bytes());
+        pack.extend_from_slice(&data);
+    }
+    let (pack_hash, pack_checksum) = get_hash_from_file(&pack);
+    pack.extend_from_slice(&pack_checksum);
+
+    let mut index = vec![];
+    index.extend_from_slice(INDEX_SIGNATURE);
+    index.extend_from_slice(&PACK_VERSION.to_be_bytes());
+    index.extend_from_slice(&(objects.len() as u32).to_be_bytes());
+    let mut fanout = [0u32; 256];
+    for (hash, _) in objects.iter() {
+        fanout[hash[0] as usize] += 1;
+    }
+    let mut total = 0;
+    for n in fanout {
+        total += n;
+        index.extend_from_slice(&total.to_be_bytes());
+    }
+    for (hash, _) in objects.iter() {
+        index.extend_from_slice(hash);
+    }
+    for offset in offsets.iter() {
+        index.extend_from_slice(&offset.to_be_bytes());
+    }
+    index.extend_from_slice(&pack_checksum);
+    let (_, index_checksum) = get_hash_from_file(&index);
+    index.extend_from_slice(&index_checksum);
+
+    let packs_path = get_packs_path(objects_path);
+    fs::create_dir_all(&packs_path)?;
+    let pack_path = packs_path.join(format!("pack-{}.pack", pack_hash));
+    let idx_path = pack_path.with_extension("idx");
+    for (path, content) in [(&pack_path, &pack), (&idx_path, &index)] {
+        let tmp_path = path.with_extension("tmp");
+        let mut f = fs::File::create(&tmp_path)?;
+        f.write_all(content)?;
+        f.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+    }
+
+    Ok(PackStats {
+        pack_path,
+        objects: objects.len(),
+        deltas: deltas.len(),
+        size: pack.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(n: usize) -> Vec<u8> {
+        (0..n).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn delta_round_trips() {
+        let base = sample(4096);
+        let mut target = base.clone();
+        target[100..110].copy_from_slice(b"0123456789");
+        target.extend_from_slice(b"appended");
+        target.drain(2000..2100);
+
+        for (b, t) in [
+            (&base, &target),
+            (&target, &base),
+            (&base, &vec![]),
+            (&vec![], &target),
+        ] {
+            let delta = create_delta(b, t);
+            assert_eq!(apply_delta(b, &delta).as_deref(), Some(&t[..]));
+        }
+    }
+
+    #[test]
+    fn delta_rejects_wrong_base() {
+        let base = sample(1024);
+        let delta = create_delta(&base, &sample(1000));
+        assert_eq!(apply_delta(&base[..1000], &delta), None);
+    }
+
+    #[test]
+    fn delta_rejects_truncated_delta() {
+        let base = sample(1024);
+        let mut target = b"prefix".to_vec();
+        target.extend_from_slice(&base);
+        let delta = create_delta(&base, &target);
+
+        for len in 0..delta.len() {
+            assert_eq!(apply_delta(&base, &delta[..len]), None, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn delta_rejects_corrupt_delta() {
+        let base = sample(64);
+
+        let mut huge_len = vec![];
+        write_varint(&mut huge_len, base.len() as u64);
+        write_varint(&mut huge_len, u64::MAX);
+        assert_eq!(apply_delta(&base, &huge_len), None);
+
+        let mut bad_op = vec![];
+        write_varint(&mut bad_op, base.len() as u64);
+        write_varint(&mut bad_op, 1);
+        bad_op.push(0xff);
+        assert_eq!(apply_delta(&base, &bad_op), None);
+
+        let mut past_base = vec![];
+        write_varint(&mut past_base, base.len() as u64);
+        write_varint(&mut past_base, 8);
+        past_base.push(DELTA_COPY);
+        write_varint(&mut past_base, 60);
+        write_varint(&mut past_base, 8);
+        assert_eq!(apply_delta(&base, &past_base), None);
+
+        let mut past_target = vec![];
+        write_varint(&mut past_target, base.len() as u64);
+        write_varint(&mut past_target, 4);
+        past_target.push(DELTA_COPY);
+        write_varint(&mut past_target, 0);
+        write_varint(&mut past_target, 8);
+        assert_eq!(apply_delta(&base, &past_target), None);
+    }
+}
@@ -0,0 +1,25 @@
+use std::path::{Component, Path};
+
+/// Turns a user supplied path into the form used in INDEX
+/// and tree entries, i.e. relative to root with a "./" prefix.
+/// A trailing "/" is dropped so directories can be matched.
+pub fn to_rit_path(path: &Path) -> String {
+    let components = path
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+
+    if components.is_empty() {
+        return ".".to_string();
+    }
+
+    format!("./{}", components.join("/"))
+}
+
+/// Checks whether `file_path` is `filter` itself or lives under it.
+pub fn is_under(file_path: &str, filter: &str) -> bool {
+    filter == "."
+        || file_path == filter
+        || (file_path.starts_with(filter) && file_path[filter.len()..].starts_with('/'))
+}
@@ -0,0 +1,311 @@
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::{collections::HashSet, fs};
+
+use walkdir::WalkDir;
+
+use crate::models::{errormodels::RitError, refmodels::Head};
+use crate::repository;
+use crate::utils::{
+    ioutils,
+    lockutils::{self, LockFile},
+};
+
+pub const DEFAULT_BRANCH: &str = "main";
+const SYMBOLIC_REF_PREFIX: &str = "ref: refs/heads/";
+
+pub fn get_heads_path() -> PathBuf {
+    repository::rit_dir().join("refs").join("heads")
+}
+
+pub fn get_head_path() -> PathBuf {
+    repository::rit_dir().join("HEAD")
+}
+
+/// Branch names follow a subset of git's rules: no empty
+/// components, no leading dash, no "..", no whitespace or
+/// special characters and no trailing ".lock".
+pub fn is_valid_branch_name(name: &str) -> bool {
+    if name.is_empty()
+        || name.starts_with('-')
+        || name.ends_with('/')
+        || name.ends_with(".lock")
+        || name.contains("..")
+        || name.contains("//")
+        || name == "HEAD"
+    {
+        return false;
+    }
+
+    !name.chars().any(|c| {
+        c.is_whitespace() || c.is_control() || matches!(c, '~' | '^' | ':' | '?' | '*' | '[' | '\\')
+    }) && name.split('/').all(|comp| !comp.starts_with('.'))
+}
+
+/// HEAD is stored either as "ref: refs/heads/<name>" or as the
+/// raw 32 bytes of a commit hash when detached. Repositories created
+/// before HEAD existed are treated as being on the default branch.
+pub fn read_head() -> io::Result<Head> {
+    let content = match fs::read(get_head_path()) {
+        Ok(c) => c,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Ok(Head::Branch(DEFAULT_BRANCH.to_string()))
+        }
+        Err(e) => return Err(e),
+    };
+
+    if let Some(name) = content.strip_prefix(SYMBOLIC_REF_PREFIX.as_bytes()) {
+        let name = String::from_utf8_lossy(name).trim_end().to_string();
+        if !is_valid_branch_name(&name) {
+            return Err(RitError::CorruptRepository(format!(
+                "HEAD points to an invalid branch name '{}'",
+                name
+            ))
+            .into());
+        }
+        return Ok(Head::Branch(name));
+    }
+
+    if content.len() == 32 {
+        return Ok(Head::Detached(content));
+    }
+
+    Err(RitError::CorruptRepository(
+        "HEAD is neither a branch nor a 32 byte commit hash".to_string(),
+    )
+    .into())
+}
+
+/// HEAD and branches are replaced through "<file>.lock".
+pub fn write_head(head: &Head) -> io::Result<()> {
+    match head {
+        Head::Branch(name) => lockutils::write_locked(
+            &get_head_path(),
+            format!("{}{}\n", SYMBOLIC_REF_PREFIX, name).as_bytes(),
+        ),
+        Head::Detached(hash) => lockutils::write_locked(&get_head_path(), hash),
+    }
+}
+
+/// Name of the checked out branch, `None` if HEAD is detached.
+pub fn current_branch() -> io::Result<Option<String>> {
+    match read_head()? {
+        Head::Branch(name) => Ok(Some(name)),
+        Head::Detached(_) => Ok(None),
+    }
+}
+
+/// The commit HEAD points to, `None` if the
+/// checked out branch has no commits yet.
+pub fn resolve_head() -> io::Result<Option<Vec<u8>>> {
+    match read_head()? {
+        Head::Branch(name) => read_branch(&name),
+        Head::Detached(hash) => Ok(Some(hash)),
+    }
+}
+
+/// Locks the ref a new commit moves, the checked out branch or the
+/// detached HEAD itself, and returns the lock with the commit the
+/// ref points to. Read under the lock, that commit stays the tip
+/// until the new one is written through the lock.
+pub fn lock_head_ref() -> io::Result<(LockFile, Option<Vec<u8>>)> {
+    let head = read_head()?;
+    let ref_path = match &head {
+        Head::Branch(name) => branch_path(name)?,
+        Head::Detached(_) => get_head_path(),
+    };
+    if let Some(parent) = ref_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let lock = LockFile::acquire(&ref_path)?;
+
+    // a checkout in the meantime would have us move the wrong ref
+    if read_head()? != head {
+        return Err(Error::new(ErrorKind::Interrupted, "HEAD moved, try again"));
+    }
+
+    Ok((lock, resolve_head()?))
+}
+
+/// Only valid names are joined to refs/heads, anything
+/// else, e.g. "../x", could point outside of it.
+fn branch_path(name: &str) -> io::Result<PathBuf> {
+    if !is_valid_branch_name(name) {
+        return Err(
+            RitError::InvalidInput(format!("'{}' is not a valid branch name", name)).into(),
+        );
+    }
+    Ok(get_heads_path().join(name))
+}
+
+/// Returns the commit hash the branch points to, or `None` if the
+/// branch does not exist (yet). Invalid names never exist, so
+/// revisions like "HEAD~1" can be tried as a branch first.
+pub fn read_branch(name: &str) -> io::Result<Option<Vec<u8>>> {
+    if !is_valid_branch_name(name) {
+        return Ok(None);
+    }
+
+    match fs::read(branch_path(name)?) {
+        Ok(hash) if hash.len() == 32 => Ok(Some(hash)),
+        Ok(_) => Err(RitError::CorruptRepository(format!(
+            "branch '{}' is not a 32 byte commit hash",
+            name
+        ))
+        .into()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn write_branch(name: &str, commit_hash: &[u8]) -> io::Result<()> {
+    let branch_path = branch_path(name)?;
+    if let Some(parent) = branch_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    lockutils::write_locked(&branch_path, commit_hash)
+}
+
+pub fn delete_branch(name: &str) -> io::Result<()> {
+    let heads_path = get_heads_path();
+    let branch_path = branch_path(name)?;
+    LockFile::acquire(&branch_path)?.remove()?;
+
+    // clean up the now empty dirs of nested names, e.g. feature/x
+    let mut dir = branch_path;
+    while let Some(parent) = dir.parent() {
+        if parent == heads_path || fs::remove_dir(parent).is_err() {
+            break;
+        }
+        dir = parent.to_path_buf();
+    }
+
+    Ok(())
+}
+
+/// All branch names under refs/heads, sorted.
+/// Nested branches are reported with "/" separators.
+pub fn list_branches() -> io::Result<Vec<String>> {
+    let heads_path = get_heads_path();
+    if !heads_path.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut branches = vec![];
+    for entry in WalkDir::new(&heads_path).into_iter().filter_map(|e| e.ok()) {
+        // "<name>.lock" is a branch being written
+        if !entry.file_type().is_file() || entry.file_name().to_string_lossy().ends_with(".lock") {
+            continue;
+        }
+        if let Ok(rel) = entry.path().strip_prefix(&heads_path) {
+            let name = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            branches.push(name);
+        }
+    }
+    branches.sort();
+
+    Ok(branches)
+}
+
+/// What every branch and HEAD point to, as ("refs/heads/<name>", hash)
+/// and ("HEAD", hash). Branches without commits are left out.
+pub fn list_ref_hashes() -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut refs = vec![];
+    for branch in list_branches()? {
+        if let Some(hash) = read_branch(&branch)? {
+            refs.push((format!("refs/heads/{}", branch), hash));
+        }
+    }
+    if let Some(hash) = resolve_head()? {
+        refs.push(("HEAD".to_string(), hash));
+    }
+
+    Ok(refs)
+}
+
+/// Resolves "HEAD", a branch name or a (possibly abbreviated,
+/// at least 4 characters) hex commit hash into a commit hash.
+/// A "~<N>" or "^" suffix walks N (or 1) first parents back.
+pub fn resolve_revision(objects_path: &Path, revision: &str) -> io::Result<Vec<u8>> {
+    if let Some(pos) = revision.rfind(['~', '^']) {
+        let steps = match (&revision[pos..pos + 1], &revision[pos + 1..]) {
+            (_, "") => Some(1),
+            ("~", n) => n.parse::<usize>().ok(),
+            _ => None,
+        };
+        if let Some(steps) = steps {
+            let mut hash = resolve_revision(objects_path, &revision[..pos])?;
+            for _ in 0..steps {
+                hash = ioutils::read_commit(objects_path, &hash)?
+                    .parent_hash
+                    .ok_or_else(|| {
+                        RitError::NotFound(format!("'{}' goes past the first commit", revision))
+                    })?;
+            }
+            return Ok(hash);
+        }
+    }
+
+    if revision == "HEAD" {
+        return resolve_head()?.ok_or_else(|| {
+            RitError::NotFound("HEAD does not point to any commit yet".to_string()).into()
+        });
+    }
+
+    if is_valid_branch_name(revision) {
+        if let Some(hash) = read_branch(revision)? {
+            return Ok(hash);
+        }
+    }
+
+    let prefix = revision.to_lowercase();
+    if prefix.len() >= 4 && prefix.len() <= 64 && prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        let mut candidates = vec![];
+        for hash in ioutils::find_objects_by_prefix(objects_path, &prefix)? {
+            let hash = hex::decode(hash).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+            if ioutils::read_commit(objects_path, &hash).is_ok() {
+                candidates.push(hash);
+            }
+        }
+
+        match candidates.len() {
+            1 => return Ok(candidates.remove(0)),
+            0 => {}
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("short hash '{}' is ambiguous", revision),
+                ))
+            }
+        }
+    }
+
+    Err(RitError::UnknownRevision(revision.to_string()).into())
+}
+
+/// Checks whether `commit_hash` can be reached by walking
+/// the parents of `tip_hash` (a commit is its own ancestor).
+pub fn is_ancestor(objects_path: &Path, commit_hash: &[u8], tip_hash: &[u8]) -> io::Result<bool> {
+    let mut visited: HashSet<Vec<u8>> = HashSet::new();
+    let mut current = Some(tip_hash.to_vec());
+
+    while let Some(hash) = current {
+        if hash == commit_hash {
+            return Ok(true);
+        }
+        if !visited.insert(hash.clone()) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "cycle in commit history",
+            ));
+        }
+
+        current = ioutils::read_commit(objects_path, &hash)?.parent_hash;
+    }
+
+    Ok(false)
+}
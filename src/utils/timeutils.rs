@@ -0,0 +1,213 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Dates `parse_date` accepts lie within the years 0 to 9999.
+const MIN_TIMESTAMP: i64 = -62_167_219_200;
+const MAX_TIMESTAMP: i64 = 253_402_300_799;
+
+/// Parses "+HHMM"/"-HHMM" into an offset in seconds,
+/// hours up to 23 and minutes up to 59.
+pub fn tz_offset_seconds(tz: &str) -> Option<i64> {
+    if tz.len() != 5 || !tz.is_ascii() {
+        return None;
+    }
+    let sign = match &tz[..1] {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let hours: i64 = tz[1..3].parse().ok()?;
+    let minutes: i64 = tz[3..5].parse().ok()?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Seconds since the unix epoch.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Accepts "+HHMM", "+HH:MM" and "Z" and returns the "+HHMM" form.
+pub fn normalize_timezone(tz: &str) -> Option<String> {
+    let tz = match tz {
+        "Z" | "z" => "+0000".to_string(),
+        _ => tz.replacen(':', "", 1),
+    };
+    tz_offset_seconds(&tz).map(|_| tz)
+}
+
+/// Converts (year, month, day) into days since 1970-01-01.
+/// Inverse of `civil_from_days`.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
+
+/// Parses a user supplied date into (timestamp, timezone). Supported forms:
+/// - "<DATE_SEC> <TIMEZONE>", e.g. "1729170000 +0200"
+/// - "@<DATE_SEC>" optionally followed by a timezone
+/// - "YYYY-MM-DD[( |T)HH:MM[:SS]][ ][TIMEZONE]", e.g. "2026-10-17T14:03:12+02:00"
+///
+/// `default_tz` is used when the date carries no timezone.
+/// Dates before year 0 or after year 9999 are rejected.
+pub fn parse_date(date: &str, default_tz: &str) -> Option<(i64, String)> {
+    let date = date.trim();
+    let (timestamp, tz) = parse_any_date(date, default_tz)?;
+
+    match (MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(&timestamp) {
+        true => Some((timestamp, tz)),
+        false => None,
+    }
+}
+
+fn parse_any_date(date: &str, default_tz: &str) -> Option<(i64, String)> {
+    let (main, tz) = match date.split_once(' ') {
+        Some((m, rest)) if normalize_timezone(rest.trim()).is_some() => {
+            (m, normalize_timezone(rest.trim()))
+        }
+        _ => (date, None),
+    };
+
+    if let Ok(sec) = main.trim_start_matches('@').parse::<i64>() {
+        return Some((sec, tz.unwrap_or_else(|| default_tz.to_string())));
+    }
+
+    parse_iso_date(date, default_tz)
+}
+
+/// Parses an expiry such as `gc.pruneExpire` into the timestamp
+/// before which things count as expired. Supported forms:
+/// - "now" (everything) and "never" (nothing)
+/// - "<N>.<UNIT>[s].ago" or "<N> <UNIT>[s] ago", e.g. "2.weeks.ago",
+///   with seconds, minutes, hours, days, weeks, months and years
+/// - any date `parse_date` accepts
+///
+/// Returns `None` for anything else, and for relative expiries
+/// reaching further back than a timestamp can.
+pub fn parse_expiry(expiry: &str, now: i64) -> Option<i64> {
+    let expiry = expiry.trim();
+    // keywords and units are case insensitive, dates are not
+    let lower = expiry.to_lowercase();
+    match lower.as_str() {
+        "now" => return Some(i64::MAX),
+        "never" => return Some(i64::MIN),
+        _ => {}
+    }
+
+    let parts: Vec<&str> = lower.split(['.', ' ']).filter(|p| !p.is_empty()).collect();
+    if let [n, unit, rest @ ..] = parts.as_slice() {
+        if let (Ok(n), true) = (n.parse::<u64>(), rest.is_empty() || rest == ["ago"]) {
+            let unit_seconds: i64 = match unit.trim_end_matches('s') {
+                "second" | "sec" => 1,
+                "minute" | "min" => 60,
+                "hour" => 3600,
+                "day" => 86400,
+                "week" => 7 * 86400,
+                "month" => 30 * 86400,
+                "year" => 365 * 86400,
+                _ => return None,
+            };
+            return i64::try_from(n)
+                .ok()
+                .and_then(|n| n.checked_mul(unit_seconds))
+                .and_then(|secs| now.checked_sub(secs));
+        }
+    }
+
+    parse_date(expiry, "+0000").map(|(timestamp, _)| timestamp)
+}
+
+fn parse_iso_date(date: &str, default_tz: &str) -> Option<(i64, String)> {
+    if date.len() < 10 || !date.is_char_boundary(10) {
+        return None;
+    }
+    let (ymd, rest) = date.split_at(10);
+    let mut ymd_parts = ymd.split('-');
+    let year: i64 = ymd_parts.next()?.parse().ok()?;
+    let month: u32 = ymd_parts.next()?.parse().ok()?;
+    let day: u32 = ymd_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let rest = rest.trim_start_matches(['T', ' ']);
+    // the time part ends where the timezone starts
+    let tz_start = rest.find(['+', '-', 'Z', 'z', ' ']).unwrap_or(rest.len());
+    let (time, tz) = rest.split_at(tz_start);
+
+    let mut secs_of_day: i64 = 0;
+    if !time.is_empty() {
+        let mut hms = time.split(':');
+        let hour: i64 = hms.next()?.parse().ok()?;
+        let minute: i64 = hms.next()?.parse().ok()?;
+        let second: i64 = hms.next().map(|s| s.parse()).unwrap_or(Ok(0)).ok()?;
+        if hms.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        secs_of_day = hour * 3600 + minute * 60 + second;
+    }
+
+    let tz = match tz.trim() {
+        "" => default_tz.to_string(),
+        t => normalize_timezone(t)?,
+    };
+
+    let local = days_from_civil(year, month, day) * 86_400 + secs_of_day;
+    Some((local - tz_offset_seconds(&tz)?, tz))
+}
+
+/// Converts days since 1970-01-01 into (year, month, day).
+/// Howard Hinnant's `civil_from_days` algorithm.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Formats a unix timestamp the way `git log` does,
+/// e.g. "Sat Oct 17 14:03:12 2026 +0200".
+pub fn format_timestamp(timestamp: i64, timezone: &str) -> String {
+    // timestamps of stored commits are not bounded like `parse_date`'s
+    let local = timestamp.saturating_add(tz_offset_seconds(timezone).unwrap_or(0));
+    let days = local.div_euclid(86_400);
+    let secs_of_day = local.rem_euclid(86_400);
+
+    let (year, month, day) = civil_from_days(days);
+    let weekday = WEEKDAYS[days.rem_euclid(7) as usize];
+
+    format!(
+        "{} {} {} {:02}:{:02}:{:02} {} {}",
+        weekday,
+        MONTHS[(month - 1) as usize],
+        day,
+        secs_of_day / 3600,
+        (secs_of_day % 3600) / 60,
+        secs_of_day % 60,
+        year,
+        timezone
+    )
+}